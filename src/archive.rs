@@ -0,0 +1,322 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io,
+    path::{Component, Path, PathBuf, MAIN_SEPARATOR_STR},
+    process::Command,
+};
+
+use axum::{routing::post, Extension, Json, Router};
+use eyre::{eyre, Context};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+use crate::{config::Settings, error::Error, files, http::{self, ApiContext}, moving::validation, roots, search::MediaArchiveGroup, trash};
+
+const ZIP_MIME: &str = "application/zip";
+const RAR_MIME: &str = "application/vnd.rar";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    ZIP,
+    RAR,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ArchiveVolume {
+    pub format: ArchiveFormat,
+    pub set_name: String,
+    pub first: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MediaExtractionResult {
+    pub path: String,
+    pub extracted: Vec<String>,
+    pub trashed: Vec<String>,
+}
+
+pub struct ArchiveDetector {
+    volume_regexes: Vec<(Regex, ArchiveFormat)>,
+}
+
+impl ArchiveDetector {
+    pub fn new() -> Self {
+        ArchiveDetector {
+            volume_regexes: vec![
+                (Regex::new(r"(?i)^(.+)\.part0*(\d+)\.rar$").unwrap(), ArchiveFormat::RAR),
+                (Regex::new(r"(?i)^(.+)\.rar()$").unwrap(), ArchiveFormat::RAR),
+                (Regex::new(r"(?i)^(.+)\.r(\d{2,3})$").unwrap(), ArchiveFormat::RAR),
+                (Regex::new(r"(?i)^(.+)\.zip()$").unwrap(), ArchiveFormat::ZIP),
+                (Regex::new(r"(?i)^(.+)\.z(\d{2,3})$").unwrap(), ArchiveFormat::ZIP),
+            ],
+        }
+    }
+
+    pub fn volume(&self, file: &Path) -> Option<ArchiveVolume> {
+        let file_name = file.file_name()?.to_string_lossy();
+        self.volume_regexes.iter()
+            .find_map(|(regex, format)| regex.captures(&file_name).map(|c| (c, *format)))
+            .map(|(c, format)| {
+                let first = match (&c[2], file_name.to_lowercase().ends_with(".rar")) {
+                    ("", _) => true,
+                    (part, true) => part.parse::<u32>().map(|p| p == 1).unwrap_or(false),
+                    (_, false) => false,
+                };
+                ArchiveVolume { format, set_name: c[1].to_owned(), first }
+            })
+    }
+
+    pub fn entry(&self, file: &Path) -> Option<ArchiveVolume> {
+        let volume = self.volume(file).filter(|v| v.first)?;
+        let expected_mime = match volume.format {
+            ArchiveFormat::ZIP => ZIP_MIME,
+            ArchiveFormat::RAR => RAR_MIME,
+        };
+
+        match infer::get_from_path(file) {
+            Ok(Some(ftype)) if ftype.mime_type() == expected_mime && !self.is_spanned_zip(file, &volume) => Some(volume),
+            _ => None,
+        }
+    }
+
+    // the zip reader can't follow an archive across .z01 volumes, so these are not offered for extraction
+    pub fn is_spanned_zip(&self, file: &Path, volume: &ArchiveVolume) -> bool {
+        volume.format == ArchiveFormat::ZIP && self.volumes_of(file, volume).map(|v| v.len() > 1).unwrap_or(false)
+    }
+
+    pub fn volumes_of(&self, entry: &Path, volume: &ArchiveVolume) -> eyre::Result<Vec<PathBuf>> {
+        let dir = entry.parent().ok_or_else(|| eyre!("archive has no parent dir: {}", entry.to_string_lossy()))?;
+
+        let mut volumes = vec![];
+        for file in fs::read_dir(dir)? {
+            let path = file?.path();
+            let same_set = self.volume(&path)
+                .map(|v| v.format == volume.format && v.set_name == volume.set_name)
+                .unwrap_or(false);
+            if same_set && path.is_file() {
+                volumes.push(path);
+            }
+        }
+
+        volumes.sort();
+        Ok(volumes)
+    }
+}
+
+impl Default for ArchiveDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn router() -> Router {
+    Router::new().route("/api/v1/media-archives/extractions", post(extract_media_archives))
+}
+
+#[utoipa::path(post, path = "/api/v1/media-archives/extractions",
+    request_body = MediaArchiveGroup,
+    responses(
+        (status = 200, description = "Extract archived media in place so it can be searched as normal media", body = MediaExtractionResult),
+        (status = 422, description = "Archive group is outside the downloads roots or holds no archives")
+    )
+)]
+pub async fn extract_media_archives(ctx: Extension<ApiContext>, Json(group): Json<MediaArchiveGroup>) -> http::Result<Json<MediaExtractionResult>> {
+    info!("extract_media_archives request received with paylod: {:?}", group);
+
+    let detector = ArchiveDetector::new();
+    let errors = validate_archive_group(&ctx.settings, &detector, &group);
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    let settings = ctx.settings.clone();
+    let result = tokio::task::spawn_blocking(move || extract(&settings, &detector, &group)).await
+        .wrap_err("extract archives task could not complete")??;
+
+    Ok(Json(result))
+}
+
+fn validate_archive_group(settings: &Settings, detector: &ArchiveDetector, group: &MediaArchiveGroup) -> Vec<(&'static str, String)> {
+    let mut errors = vec![];
+
+    let src = files::canonicalize(Path::new(&group.path));
+    if !Path::new(&group.path).is_absolute() || roots::downloads_root_of(settings, &src).is_none() {
+        errors.push(("path", format!("archive path is outside the downloads roots: {}", &group.path)));
+    }
+
+    if roots::is_downloads_root(settings, &src) && !validation::is_single_segment(&group.name) {
+        errors.push(("name", format!("archive name should be a single folder name: {}", &group.name)));
+    }
+
+    if group.archives.is_empty() {
+        errors.push(("archives", "archive group has no archives".to_owned()));
+    }
+
+    for archive in &group.archives {
+        let is_relative = Path::new(archive).components().all(|c| matches!(c, Component::Normal(_)));
+        let path = src.join(archive);
+        if !is_relative || archive.is_empty() || !files::canonicalize(&path).starts_with(&src) {
+            errors.push(("archives", format!("archive should be a relative path inside the archive path: {}", archive)));
+        } else if detector.volume(&path).filter(|v| detector.is_spanned_zip(&path, v)).is_some() {
+            errors.push(("archives", format!("spanned zip archives are not supported: {}", archive)));
+        } else if detector.entry(&path).is_none() {
+            errors.push(("archives", format!("archive is not the first volume of a supported archive: {}", archive)));
+        }
+    }
+
+    errors
+}
+
+pub fn extract(settings: &Settings, detector: &ArchiveDetector, group: &MediaArchiveGroup) -> eyre::Result<MediaExtractionResult> {
+    let group_path = PathBuf::from(&group.path);
+    let dest_root = match roots::is_downloads_root(settings, &group_path) {
+        true => group_path.join(&group.name),
+        false => group_path.clone(),
+    };
+
+    let mut extracted = vec![];
+    let mut volumes = vec![];
+    for archive in &group.archives {
+        let archive_path = group_path.join(archive);
+        let volume = detector.entry(&archive_path)
+            .ok_or_else(|| eyre!("not a supported archive: {}", archive_path.to_string_lossy()))?;
+        let out_dir = match Path::new(archive).parent() {
+            Some(parent) => dest_root.join(parent),
+            None => dest_root.clone(),
+        };
+
+        let files = match volume.format {
+            ArchiveFormat::ZIP => extract_zip(&archive_path, &out_dir)?,
+            ArchiveFormat::RAR => extract_rar(settings, &archive_path, &out_dir)?,
+        };
+        info!("extracted {} files from archive {}", files.len(), archive_path.to_string_lossy());
+
+        extracted.extend(files);
+        volumes.extend(detector.volumes_of(&archive_path, &volume)?);
+    }
+
+    let mut trashed = vec![];
+    if settings.archive.trash_after_extract {
+        for volume in volumes {
+            trash::move_to_trash(settings, &volume)?;
+            trashed.push(volume.to_string_lossy().into_owned());
+        }
+    }
+
+    Ok(MediaExtractionResult {
+        path: dest_root.to_string_lossy().into_owned(),
+        extracted: extracted.iter()
+            .map(|f| f.strip_prefix(&dest_root).unwrap_or(f).to_string_lossy().into_owned())
+            .collect(),
+        trashed,
+    })
+}
+
+fn extract_zip(archive: &Path, out_dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let file = File::open(archive).wrap_err_with(|| format!("could not open archive {}", archive.to_string_lossy()))?;
+    let mut zip = ZipArchive::new(file).wrap_err_with(|| format!("could not read zip {}", archive.to_string_lossy()))?;
+
+    // existing files are never overwritten, checking them all first avoids a half extracted archive
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i)?;
+        if let Some(out) = entry.enclosed_name().map(|name| out_dir.join(name)).filter(|out| !entry.is_dir() && out.exists()) {
+            return Err(eyre!("zip {} would overwrite existing file {}", archive.to_string_lossy(), out.to_string_lossy()));
+        }
+    }
+
+    let mut extracted = vec![];
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let out = match entry.enclosed_name() {
+            Some(name) => out_dir.join(name),
+            None => {
+                warn!("skipping zip entry {} escaping the extraction dir", entry.name());
+                continue;
+            },
+        };
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out)?;
+            continue;
+        }
+
+        files::create_parent(&out)?;
+        extract_zip_entry(&mut entry, &out)?;
+        extracted.push(out);
+    }
+
+    Ok(extracted)
+}
+
+// entries are written to a partial file first, so a failed entry never leaves a truncated file behind
+fn extract_zip_entry(entry: &mut impl io::Read, out: &Path) -> eyre::Result<()> {
+    let partial = files::partial_path(out);
+
+    let res = File::options().write(true).create_new(true).open(&partial)
+        .and_then(|mut file| io::copy(entry, &mut file).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&partial, out))
+        .wrap_err_with(|| format!("could not extract zip entry to {}", out.to_string_lossy()));
+
+    if res.is_err() && partial.exists() {
+        if let Err(e) = fs::remove_file(&partial) {
+            info!("could not remove partial zip entry {}: {:?}", partial.to_string_lossy(), e);
+        }
+    }
+
+    res
+}
+
+fn extract_rar(settings: &Settings, archive: &Path, out_dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    fs::create_dir_all(out_dir)?;
+    let existing: BTreeSet<PathBuf> = files::walk_files(out_dir, u8::MAX)?.into_iter().map(|f| f.into_path()).collect();
+
+    // unrar skips existing files with -o-, so they are checked first to fail instead of extracting part of the archive
+    for name in list_rar(settings, archive)? {
+        let out = out_dir.join(&name);
+        if out.is_file() {
+            return Err(eyre!("rar {} would overwrite existing file {}", archive.to_string_lossy(), out.to_string_lossy()));
+        }
+    }
+
+    // unrar treats the destination as a dir only when it ends with a separator
+    let mut dest = out_dir.as_os_str().to_owned();
+    dest.push(MAIN_SEPARATOR_STR);
+
+    // broken files are deleted by unrar unless asked to keep them with -kb
+    let output = Command::new(&settings.archive.unrar_command)
+        .args(["x", "-o-", "-y"])
+        .arg(archive)
+        .arg(&dest)
+        .output()
+        .wrap_err_with(|| format!("could not run {} for archive {}", &settings.archive.unrar_command, archive.to_string_lossy()))?;
+
+    if !output.status.success() {
+        return Err(eyre!("could not extract rar {}: {}", archive.to_string_lossy(), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(files::walk_files(out_dir, u8::MAX)?.into_iter()
+        .map(|f| f.into_path())
+        .filter(|f| !existing.contains(f))
+        .collect())
+}
+
+fn list_rar(settings: &Settings, archive: &Path) -> eyre::Result<Vec<String>> {
+    let output = Command::new(&settings.archive.unrar_command)
+        .args(["lb"])
+        .arg(archive)
+        .output()
+        .wrap_err_with(|| format!("could not run {} for archive {}", &settings.archive.unrar_command, archive.to_string_lossy()))?;
+
+    if !output.status.success() {
+        return Err(eyre!("could not list rar {}: {}", archive.to_string_lossy(), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_owned())
+        .collect())
+}
@@ -0,0 +1,273 @@
+use std::{collections::HashSet, path::Path, sync::Arc, time::Duration};
+
+use axum::{routing::post, Extension, Json, Router};
+use eyre::Context;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    config::Settings, episode::EpisodeParser, error::Error, http::{self, ApiContext}, moving::validation,
+    rename::{external::ExternalSearcher, name::NameGenerator, MediaFileType}, search::MediaFileGroup, tmdb::TmdbAPI,
+};
+
+use self::duration::media_duration;
+
+pub mod duration;
+
+const EPISODE_MIN_DURATION: Duration = Duration::from_secs(10 * 60);
+const EPISODE_MAX_DURATION: Duration = Duration::from_secs(65 * 60);
+const MOVIE_MIN_DURATION: Duration = Duration::from_secs(75 * 60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct MediaClassification {
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    #[schema(rename = "type")]
+    pub media_type: MediaFileType,
+    pub confidence: u8,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ClassificationScore {
+    tv_score: f64,
+    reasons: Vec<String>,
+}
+
+impl ClassificationScore {
+    fn add(&mut self, tv_score: f64, reason: String) {
+        self.tv_score += tv_score;
+        self.reasons.push(reason);
+    }
+}
+
+impl From<ClassificationScore> for MediaClassification {
+    fn from(score: ClassificationScore) -> Self {
+        let media_type = match score.tv_score > 0.0 {
+            true => MediaFileType::TV,
+            false => MediaFileType::MOVIE,
+        };
+        let confidence = (100.0 / (1.0 + (-score.tv_score.abs()).exp())).round() as u8;
+
+        MediaClassification { media_type, confidence, reasons: score.reasons }
+    }
+}
+
+pub struct MediaClassifier {
+    generator: NameGenerator,
+    episode_parser: EpisodeParser,
+    season_regex: Regex,
+}
+
+impl MediaClassifier {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        MediaClassifier {
+            generator: NameGenerator::new(settings),
+            episode_parser: EpisodeParser::new(),
+            season_regex: Regex::new(r"(?i)(^|[^a-z0-9])(s\d{1,2}|season[ ._-]?\d{1,2}|complete[ ._-]series)([^a-z0-9]|$)").unwrap(),
+        }
+    }
+
+    pub fn file_score(&self, group: &MediaFileGroup) -> ClassificationScore {
+        let mut score = self.name_score(group);
+        self.duration_score(group, &mut score);
+        score
+    }
+
+    // only looks at names, cheap enough to run on every search
+    pub fn name_score(&self, group: &MediaFileGroup) -> ClassificationScore {
+        let mut score = ClassificationScore::default();
+        let videos_count = group.videos.len();
+
+        if self.season_regex.is_match(&group.name) {
+            score.add(2.0, format!("name {} looks like a season", &group.name));
+        }
+
+        let episodes: Vec<(Option<u32>, Option<u32>)> = group.videos.iter()
+            .map(|v| self.episode_parser.parse(&Path::new(&group.name).join(v)))
+            .collect();
+        let with_season = episodes.iter().filter(|(s, e)| s.is_some() && e.is_some()).count();
+        let with_episode = episodes.iter().filter(|(_, e)| e.is_some()).count();
+
+        if with_season > 0 {
+            score.add(3.0, format!("{} of {} videos have season and episode numbers", with_season, videos_count));
+        } else if with_episode > 0 {
+            score.add(1.5, format!("{} of {} videos have episode numbers", with_episode, videos_count));
+        }
+
+        let distinct_episodes: HashSet<&(Option<u32>, Option<u32>)> = episodes.iter().filter(|(_, e)| e.is_some()).collect();
+        if distinct_episodes.len() > 1 {
+            score.add(1.5, format!("{} distinct episodes found", distinct_episodes.len()));
+        } else if videos_count == 1 && with_episode == 0 {
+            score.add(-1.5, "single video without episode numbers".to_owned());
+        }
+
+        let in_season_dirs = group.videos.iter()
+            .flat_map(|v| Path::new(v).parent().into_iter().flat_map(|p| p.iter()))
+            .any(|d| self.season_regex.is_match(&d.to_string_lossy()));
+        if in_season_dirs {
+            score.add(2.0, "videos are grouped in season folders".to_owned());
+        }
+
+        score
+    }
+
+    // reads the container of every video to find its runtime
+    fn duration_score(&self, group: &MediaFileGroup, score: &mut ClassificationScore) {
+        let mut durations: Vec<Duration> = group.videos.iter()
+            .filter_map(|v| media_duration(&Path::new(&group.path).join(v)))
+            .filter(|d| *d >= EPISODE_MIN_DURATION)
+            .collect();
+        durations.sort();
+        if let Some(median) = durations.get(durations.len() / 2) {
+            let minutes = median.as_secs() / 60;
+            if *median >= MOVIE_MIN_DURATION {
+                score.add(-2.0, format!("runtime of {} minutes fits a movie", minutes));
+            } else if *median <= EPISODE_MAX_DURATION {
+                score.add(1.5, format!("runtime of {} minutes fits an episode", minutes));
+            }
+        }
+    }
+
+    pub async fn provider_score(&self, searcher: &dyn ExternalSearcher, name: &str, score: &mut ClassificationScore) {
+        let base_info = self.generator.generate_base_info(name.to_owned());
+        let (movies, shows) = futures::join!(
+            searcher.search_movie(base_info.name(), base_info.year()),
+            searcher.search_tv(base_info.name(), base_info.year())
+        );
+
+        let (movies, shows) = match (movies, shows) {
+            (Ok(movies), Ok(shows)) => (movies, shows),
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("could not search metadata provider to classify {}: {:?}", name, e);
+                return;
+            },
+        };
+
+        let title = self.generator.comparable_name(base_info.name());
+        let movie_match = movies.iter().any(|m| self.generator.comparable_name(&m.title) == title);
+        let show_match = shows.iter().any(|s| self.generator.comparable_name(&s.title) == title);
+
+        match (movie_match, show_match, movies.is_empty(), shows.is_empty()) {
+            (true, false, _, _) => score.add(-2.0, format!("metadata provider knows a movie titled {}", base_info.name())),
+            (false, true, _, _) => score.add(2.0, format!("metadata provider knows a show titled {}", base_info.name())),
+            (false, false, false, true) => score.add(-1.0, "metadata provider only found movies".to_owned()),
+            (false, false, true, false) => score.add(1.0, "metadata provider only found shows".to_owned()),
+            _ => (),
+        }
+    }
+}
+
+pub fn router() -> Router {
+    Router::new().route("/api/v1/media-classifications", post(classify_media_group))
+}
+
+#[utoipa::path(post, path = "/api/v1/media-classifications",
+    request_body = MediaFileGroup,
+    responses(
+        (status = 200, description = "Propose whether given media is a movie or a TV show, with a confidence score", body = MediaClassification),
+        (status = 422, description = "Paths of the media are outside the downloads root")
+    )
+)]
+pub async fn classify_media_group(ctx: Extension<ApiContext>, Json(group): Json<MediaFileGroup>) -> http::Result<Json<MediaClassification>> {
+    info!("classify_media_group request received with paylod: {:?}", group);
+
+    let errors = validation::validate_file_group(&ctx.settings, &group, ("path", "videos"));
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    Ok(Json(classify_media(ctx.settings.clone(), group).await?))
+}
+
+pub async fn classify_media(settings: Arc<Settings>, group: MediaFileGroup) -> eyre::Result<MediaClassification> {
+    let classifier = Arc::new(MediaClassifier::new(settings.clone()));
+    let name = group.name.clone();
+
+    let file_classifier = classifier.clone();
+    let mut score = tokio::task::spawn_blocking(move || file_classifier.file_score(&group)).await
+        .wrap_err("classify task could not complete")?;
+
+    if settings.classify.use_metadata_provider {
+        classifier.provider_score(&TmdbAPI::new(settings.clone()), &name, &mut score).await;
+    }
+
+    let classification = MediaClassification::from(score);
+    info!("classified media {} as {:?}", name, classification);
+    Ok(classification)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use crate::{rename::{external::{ExternalMedia, ExternalSearcher}, MediaFileType}, search::MediaFileGroup, tests::create_test_settings};
+
+    use super::{ClassificationScore, MediaClassification, MediaClassifier};
+
+    struct StubSearcher {
+        movies: Vec<&'static str>,
+        shows: Vec<&'static str>,
+    }
+
+    fn external_media(title: &str) -> ExternalMedia {
+        ExternalMedia { title: title.to_owned(), poster_path: None, backdrop_path: None, date: String::new(), description: String::new(), id: 1, cast: vec![], actors: vec![] }
+    }
+
+    #[async_trait]
+    impl ExternalSearcher for StubSearcher {
+        async fn search_tv(&self, _query: &str, _year: Option<i32>) -> eyre::Result<Vec<ExternalMedia>> {
+            Ok(self.shows.iter().map(|t| external_media(t)).collect())
+        }
+
+        async fn search_movie(&self, _query: &str, _year: Option<i32>) -> eyre::Result<Vec<ExternalMedia>> {
+            Ok(self.movies.iter().map(|t| external_media(t)).collect())
+        }
+    }
+
+    fn group(name: &str, videos: &[&str]) -> MediaFileGroup {
+        MediaFileGroup {
+            path: "/nowhere".to_owned(),
+            name: name.to_owned(),
+            videos: videos.iter().map(|v| v.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn media_classified_from_files() {
+        let classifier = MediaClassifier::new(Arc::new(create_test_settings()));
+
+        let show = MediaClassification::from(classifier.file_score(&group("Show.S01.1080p", &["Show.S01E01.mkv", "Show.S01E02.mkv"])));
+        assert_eq!(MediaFileType::TV, show.media_type);
+        assert!(show.confidence > 95);
+
+        let seasons = MediaClassification::from(classifier.file_score(&group("Show", &["Season 1/01.mkv", "Season 2/01.mkv"])));
+        assert_eq!(MediaFileType::TV, seasons.media_type);
+
+        let movie = MediaClassification::from(classifier.file_score(&group("Movie.2020.1080p", &["movie.2020.1080p.mkv"])));
+        assert_eq!(MediaFileType::MOVIE, movie.media_type);
+        assert!(movie.confidence > 60 && movie.confidence < show.confidence);
+
+        let unclear = MediaClassification::from(classifier.file_score(&group("Something", &["a.mkv", "b.mkv"])));
+        assert_eq!(50, unclear.confidence);
+    }
+
+    #[tokio::test]
+    async fn media_classified_from_provider() {
+        let classifier = MediaClassifier::new(Arc::new(create_test_settings()));
+
+        let mut score = ClassificationScore::default();
+        let searcher = StubSearcher { movies: vec!["Other Movie"], shows: vec!["The Office"] };
+        classifier.provider_score(&searcher, "The.Office.2005.720p", &mut score).await;
+        assert_eq!(MediaFileType::TV, MediaClassification::from(score).media_type);
+
+        let mut score = ClassificationScore::default();
+        let searcher = StubSearcher { movies: vec!["Heat"], shows: vec![] };
+        classifier.provider_score(&searcher, "Heat (1995)", &mut score).await;
+        assert_eq!(MediaFileType::MOVIE, MediaClassification::from(score).media_type);
+    }
+}
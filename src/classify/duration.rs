@@ -0,0 +1,220 @@
+use std::{fs::File, io::{BufReader, Read, Seek, SeekFrom}, path::Path, time::Duration};
+
+const MAX_ELEMENTS: usize = 256;
+
+const EBML_ID: u32 = 0x1A45DFA3;
+const SEGMENT_ID: u32 = 0x18538067;
+const INFO_ID: u32 = 0x1549A966;
+const TIMECODE_SCALE_ID: u32 = 0x2AD7B1;
+const DURATION_ID: u32 = 0x4489;
+const DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+pub fn media_duration(path: &Path) -> Option<Duration> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).ok()?;
+    reader.rewind().ok()?;
+
+    match (&magic[..4], &magic[4..]) {
+        ([0x1A, 0x45, 0xDF, 0xA3], _) => matroska_duration(&mut reader),
+        (_, b"ftyp") => mp4_duration(&mut reader),
+        _ => None,
+    }
+}
+
+fn mp4_duration<R: Read + Seek>(reader: &mut R) -> Option<Duration> {
+    let moov_end = find_mp4_box(reader, b"moov", u64::MAX)?;
+    find_mp4_box(reader, b"mvhd", moov_end)?;
+
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version).ok()?;
+    let (timescale, duration) = match version[0] {
+        1 => {
+            reader.seek(SeekFrom::Current(16)).ok()?;
+            (read_uint(reader, 4)?, read_uint(reader, 8)?)
+        },
+        _ => {
+            reader.seek(SeekFrom::Current(8)).ok()?;
+            (read_uint(reader, 4)?, read_uint(reader, 4)?)
+        },
+    };
+
+    match timescale {
+        0 => None,
+        t => Some(Duration::from_millis(duration.saturating_mul(1000) / t)),
+    }
+}
+
+// leaves the reader at the start of the found box content and returns where the box ends
+fn find_mp4_box<R: Read + Seek>(reader: &mut R, box_type: &[u8; 4], end: u64) -> Option<u64> {
+    for _ in 0..MAX_ELEMENTS {
+        let start = reader.stream_position().ok()?;
+        if start >= end {
+            return None;
+        }
+
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let size = match u32::from_be_bytes(header[..4].try_into().ok()?) as u64 {
+            0 => return None,
+            1 => read_uint(reader, 8)?,
+            size => size,
+        };
+
+        let content_start = reader.stream_position().ok()?;
+        let box_end = start.checked_add(size)?;
+        if box_end < content_start {
+            return None;
+        }
+        if &header[4..] == box_type {
+            return Some(box_end);
+        }
+        reader.seek(SeekFrom::Start(box_end)).ok()?;
+    }
+    None
+}
+
+fn matroska_duration<R: Read + Seek>(reader: &mut R) -> Option<Duration> {
+    let (id, size) = read_ebml_header(reader)?;
+    if id != EBML_ID {
+        return None;
+    }
+    reader.seek(SeekFrom::Current(size? as i64)).ok()?;
+
+    let (id, _) = read_ebml_header(reader)?;
+    if id != SEGMENT_ID {
+        return None;
+    }
+
+    for _ in 0..MAX_ELEMENTS {
+        let (id, size) = read_ebml_header(reader)?;
+        if id == INFO_ID {
+            return matroska_info_duration(reader, size?);
+        }
+        reader.seek(SeekFrom::Current(size? as i64)).ok()?;
+    }
+    None
+}
+
+fn matroska_info_duration<R: Read + Seek>(reader: &mut R, info_size: u64) -> Option<Duration> {
+    let info_end = reader.stream_position().ok()?.checked_add(info_size)?;
+    let mut timecode_scale = DEFAULT_TIMECODE_SCALE;
+    let mut duration = None;
+
+    while reader.stream_position().ok()? < info_end {
+        let (id, size) = read_ebml_header(reader)?;
+        let size = size?;
+        match id {
+            TIMECODE_SCALE_ID => timecode_scale = read_uint(reader, size as usize)?,
+            DURATION_ID => duration = Some(read_float(reader, size as usize)?),
+            _ => {
+                reader.seek(SeekFrom::Current(size as i64)).ok()?;
+            },
+        }
+    }
+
+    let nanos = duration? * timecode_scale as f64;
+    match nanos.is_finite() && nanos >= 0.0 {
+        true => Some(Duration::from_nanos(nanos as u64)),
+        false => None,
+    }
+}
+
+// the element size is None when the muxer left it unknown, as live streams do for the segment
+fn read_ebml_header<R: Read>(reader: &mut R) -> Option<(u32, Option<u64>)> {
+    let (id, _) = read_vint(reader, 4)?;
+    let (size, len) = read_vint(reader, 8)?;
+
+    let value_mask = (1u64 << (7 * len)) - 1;
+    let size = match size & value_mask == value_mask {
+        true => None,
+        false => Some(size & value_mask),
+    };
+    Some((id as u32, size))
+}
+
+// returns the raw value including the length marker, as EBML ids keep it
+fn read_vint<R: Read>(reader: &mut R, max_len: u32) -> Option<(u64, u32)> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first).ok()?;
+
+    let len = first[0].leading_zeros() + 1;
+    if len > max_len {
+        return None;
+    }
+
+    let mut value = first[0] as u64;
+    for _ in 1..len {
+        let mut next = [0u8; 1];
+        reader.read_exact(&mut next).ok()?;
+        value = (value << 8) | next[0] as u64;
+    }
+    Some((value, len))
+}
+
+fn read_uint<R: Read>(reader: &mut R, len: usize) -> Option<u64> {
+    if len > 8 {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf[8 - len..]).ok()?;
+    Some(u64::from_be_bytes(buf))
+}
+
+fn read_float<R: Read>(reader: &mut R, len: usize) -> Option<f64> {
+    match len {
+        4 => Some(f32::from_bits(read_uint(reader, 4)? as u32) as f64),
+        8 => Some(f64::from_bits(read_uint(reader, 8)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::media_duration;
+
+    fn mp4_with_duration(duration: Duration) -> Vec<u8> {
+        let mut mvhd = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+        mvhd.extend_from_slice(&(duration.as_millis() as u32).to_be_bytes());
+        mvhd.extend_from_slice(&[0; 80]);
+
+        let mut bytes = vec![0, 0, 0, 0x14];
+        bytes.extend_from_slice(b"ftypisom\0\0\x02\0isom");
+        bytes.extend_from_slice(&(8 + 8 + mvhd.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(b"moov");
+        bytes.extend_from_slice(&(8 + mvhd.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(b"mvhd");
+        bytes.extend_from_slice(&mvhd);
+        bytes
+    }
+
+    fn mkv_with_duration(duration: Duration) -> Vec<u8> {
+        let mut bytes = vec![0x1A, 0x45, 0xDF, 0xA3, 0x84, 0x42, 0x82, 0x81, 0x00];
+        bytes.extend_from_slice(&[0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        bytes.extend_from_slice(&[0xEC, 0x82, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0x15, 0x49, 0xA9, 0x66, 0x8E]);
+        bytes.extend_from_slice(&[0x2A, 0xD7, 0xB1, 0x83, 0x0F, 0x42, 0x40]);
+        bytes.extend_from_slice(&[0x44, 0x89, 0x84]);
+        bytes.extend_from_slice(&((duration.as_millis() as f32).to_bits()).to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn duration_read_from_containers() {
+        let dir = std::env::temp_dir().join(format!("{:032x}", rand::random::<u128>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let runtime = Duration::from_secs(42 * 60);
+
+        std::fs::write(dir.join("video.mp4"), mp4_with_duration(runtime)).unwrap();
+        std::fs::write(dir.join("video.mkv"), mkv_with_duration(runtime)).unwrap();
+        std::fs::write(dir.join("video.avi"), b"RIFF\0\0\0\0AVI LIST").unwrap();
+
+        assert_eq!(Some(runtime), media_duration(&dir.join("video.mp4")));
+        assert_eq!(Some(runtime), media_duration(&dir.join("video.mkv")));
+        assert_eq!(None, media_duration(&dir.join("video.avi")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
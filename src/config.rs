@@ -1,11 +1,15 @@
 use serde::Deserialize;
 
+use crate::{moving::{release::ReleaseFileAction, ImportMode}, rename::MediaFileType};
+
 #[derive(Debug, Deserialize)]
 pub struct MongoDbConfig {
     pub connection_url: String,
     pub database: String,
     pub download_collection: String,
     pub online_collection: String,
+    pub move_job_collection: String,
+    pub move_journal_collection: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,12 +20,25 @@ pub struct TmdbConfig {
     pub movie_credits_url: String,
     pub search_tv_url: String,
     pub tv_credits_url: String,
+    pub tv_details_url: String,
+    pub tv_season_url: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OnlineConfig {
     pub result_limit: u16,
     pub poster_base: String,
+    pub fanart_base: String,
+    pub full_poster_base: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImagesConfig {
+    pub proxy: bool,
+    pub public_base: String,
+    pub path: String,
+    pub max_bytes: u64,
+    pub max_image_bytes: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,11 +47,33 @@ pub struct ServerConfig {
     pub command_listen_cron: String,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct NamedRoot {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LibraryRoot {
+    pub name: String,
+    pub path: String,
+    pub media_type: MediaFileType,
+    #[serde(default)]
+    pub resolutions: Vec<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub name_range: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FilesystemConfig {
     pub downloads_path: String,
     pub movies_path: String,
     pub tv_path: String,
+    pub downloads_roots: Vec<NamedRoot>,
+    pub library_roots: Vec<LibraryRoot>,
+    pub prefer_free_space: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,6 +82,10 @@ pub struct SearchConfig {
     pub exclude_paths: Vec<String>,
     pub video_min_size_bytes: u64,
     pub video_mime_types: Vec<String>,
+    pub incomplete_ext: Vec<String>,
+    pub quiet_period_secs: u64,
+    pub sample_regex: String,
+    pub parallelism: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -52,11 +95,61 @@ pub struct RenameConfig {
     pub similarity_percent: u8,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ReleaseActionsConfig {
+    pub extra: ReleaseFileAction,
+    pub sample: ReleaseFileAction,
+    pub metadata: ReleaseFileAction,
+    pub junk: ReleaseFileAction,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MoveConfig {
     pub subs_max_depth: u8,
     pub restricted_remove_paths: Vec<String>,
     pub subs_ext: Vec<String>,
+    pub subs_normalize_encoding: bool,
+    pub subs_encodings: Vec<String>,
+    pub subs_convert_to_srt: bool,
+    pub subs_default_fps: f64,
+    pub metadata_ext: Vec<String>,
+    pub verify_checksum: bool,
+    pub batch_concurrency: u8,
+    pub import_mode: ImportMode,
+    pub free_space_reserve_bytes: u64,
+    pub write_nfo: bool,
+    pub download_artwork: bool,
+    pub artwork_max_bytes: u64,
+    pub job_page_size: u64,
+    pub max_job_page_size: u64,
+    pub release_actions: ReleaseActionsConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrashConfig {
+    pub path: String,
+    pub retention_days: u16,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryConfig {
+    pub max_depth: u8,
+    pub page_size: usize,
+    pub max_page_size: usize,
+    pub ignore_specials: bool,
+    pub ignore_unaired: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveConfig {
+    pub unrar_command: String,
+    pub trash_after_extract: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClassifyConfig {
+    pub use_metadata_provider: bool,
+    pub move_min_confidence: u8,
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,10 +157,15 @@ pub struct Settings {
     pub mongodb: MongoDbConfig,
     pub tmdb: TmdbConfig,
     pub online: OnlineConfig,
+    pub images: ImagesConfig,
     pub server: ServerConfig,
     pub filesystem: FilesystemConfig,
     pub search: SearchConfig,
     pub rename: RenameConfig,
     pub mv: MoveConfig,
+    pub trash: TrashConfig,
+    pub archive: ArchiveConfig,
+    pub library: LibraryConfig,
+    pub classify: ClassifyConfig,
     pub server_port: u16,
 }
\ No newline at end of file
@@ -1,17 +1,19 @@
 use std::sync::Arc;
 
-use crate::{download::DownloadCacheRepo, rename::cache::OnlineCacheRepo};
+use crate::{download::DownloadCacheRepo, rename::cache::OnlineCacheRepo, moving::{job::MoveJobRepo, journal::MoveJournalRepo}};
 
 // Add more repos as needed
 
-pub trait DbWrapper: DownloadCacheRepo + OnlineCacheRepo 
+pub trait DbWrapper: DownloadCacheRepo + OnlineCacheRepo + MoveJobRepo + MoveJournalRepo 
 {
     fn download_cache_repo(&self) -> &dyn DownloadCacheRepo;
     fn online_cache_repo(&self) -> &dyn OnlineCacheRepo;
+    fn move_job_repo(&self) -> &dyn MoveJobRepo;
+    fn move_journal_repo(&self) -> &dyn MoveJournalRepo;
 }
 
 impl <R> DbWrapper for R 
-    where R: DownloadCacheRepo + OnlineCacheRepo 
+    where R: DownloadCacheRepo + OnlineCacheRepo + MoveJobRepo + MoveJournalRepo 
 {
     fn download_cache_repo(&self) -> &dyn DownloadCacheRepo {
         self
@@ -20,6 +22,14 @@ impl <R> DbWrapper for R
     fn online_cache_repo(&self) -> &dyn OnlineCacheRepo {
         self
     }
+
+    fn move_job_repo(&self) -> &dyn MoveJobRepo {
+        self
+    }
+
+    fn move_journal_repo(&self) -> &dyn MoveJournalRepo {
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -39,4 +49,12 @@ impl DbClient {
     pub fn online_cache_repo(&self) -> &dyn OnlineCacheRepo {
         self.db.online_cache_repo()
     }
+
+    pub fn move_job_repo(&self) -> &dyn MoveJobRepo {
+        self.db.move_job_repo()
+    }
+
+    pub fn move_journal_repo(&self) -> &dyn MoveJournalRepo {
+        self.db.move_journal_repo()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use regex::Regex;
+
+pub struct EpisodeParser {
+    season_episode_regex: Regex,
+    episode_regex: Regex,
+}
+
+impl EpisodeParser {
+    pub fn new() -> Self {
+        EpisodeParser {
+            season_episode_regex: Regex::new(r"(?i)(?:s(\d{1,2})[ ._-]?e(\d{1,3})|\b(\d{1,2})x(\d{2,3})\b)").unwrap(),
+            episode_regex: Regex::new(r"(?i)(?:^|[^a-z])e(?:p|pisode)?[ ._-]?(\d{1,3})(?:[^0-9]|$)").unwrap(),
+        }
+    }
+
+    pub fn parse(&self, path: &Path) -> (Option<u32>, Option<u32>) {
+        for segment in path.iter().rev().map(|s| s.to_string_lossy()) {
+            if let Some(c) = self.season_episode_regex.captures(&segment) {
+                let season = c.get(1).or(c.get(3)).and_then(|m| m.as_str().parse().ok());
+                let episode = c.get(2).or(c.get(4)).and_then(|m| m.as_str().parse().ok());
+                return (season, episode);
+            }
+            if let Some(c) = self.episode_regex.captures(&segment) {
+                return (None, c.get(1).and_then(|m| m.as_str().parse().ok()));
+            }
+        }
+        (None, None)
+    }
+}
+
+impl Default for EpisodeParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,19 @@
-use std::{path::Path, fs};
+use std::{path::{Component, Path, PathBuf}, fs::{self, File}, io::{self, BufReader, BufWriter, Read, Write}};
 
 use eyre::{eyre, Context};
+use regex::Regex;
+use tracing::info;
 use walkdir::{WalkDir, DirEntry};
 
+const COPY_BUF_SIZE: usize = 1024 * 1024;
+const PARTIAL_EXT: &str = "partial";
+
+// ERROR_NOT_SAME_DEVICE
+#[cfg(windows)]
+const CROSS_DEVICE_ERROR: i32 = 17;
+#[cfg(not(windows))]
+const CROSS_DEVICE_ERROR: i32 = libc::EXDEV;
+
 enum WalkOptions {
     OnlyFiles,
     OnlyDirectories,
@@ -39,17 +50,336 @@ fn walk(path: &Path, max_depth: u8, options: WalkOptions) -> eyre::Result<Vec<Di
     )
 }
 
-pub fn move_files(src: &Path, dest: &Path) -> eyre::Result<()> {
-    match dest.parent() {
+pub fn move_files(src: &Path, dest: &Path, verify_checksum: bool) -> eyre::Result<()> {
+    move_files_with_progress(src, dest, verify_checksum, &mut |_| ())
+}
+
+pub fn move_files_with_progress(src: &Path, dest: &Path, verify_checksum: bool, progress: &mut dyn FnMut(u64)) -> eyre::Result<()> {
+    create_parent(dest)?;
+
+    match fs::rename(src, dest) {
+        Ok(_) => Ok(()),
+        Err(e) if is_cross_device(&e) => {
+            info!("path {} is on another filesystem than {}, falling back to copy", 
+                src.to_string_lossy(), dest.to_string_lossy());
+            copy_verified(src, dest, verify_checksum, progress)?;
+            fs::remove_file(src).wrap_err_with(|| 
+                format!("could not remove source {} after copying it", src.to_string_lossy()))
+        },
+        Err(e) => Err(e).wrap_err_with(|| 
+            format!("could not move path {} to {}", src.to_string_lossy(), dest.to_string_lossy())),
+    }
+}
+
+pub fn move_path(src: &Path, dest: &Path, verify_checksum: bool) -> eyre::Result<()> {
+    if !src.is_dir() {
+        return move_files(src, dest, verify_checksum);
+    }
+
+    create_parent(dest)?;
+
+    match fs::rename(src, dest) {
+        Ok(_) => Ok(()),
+        Err(e) if is_cross_device(&e) => {
+            info!("dir {} is on another filesystem than {}, falling back to copying it", 
+                src.to_string_lossy(), dest.to_string_lossy());
+            copy_dir_verified(src, dest, verify_checksum)?;
+            delete_dir(src)
+        },
+        Err(e) => Err(e).wrap_err_with(|| 
+            format!("could not move dir {} to {}", src.to_string_lossy(), dest.to_string_lossy())),
+    }
+}
+
+// links are recreated as links and empty dirs are kept, so the copy matches the source tree as it is
+pub fn copy_dir_verified(src: &Path, dest: &Path, verify_checksum: bool) -> eyre::Result<()> {
+    for entry in WalkDir::new(src).follow_links(false).sort_by_file_name() {
+        let entry = entry.wrap_err_with(|| format!("could not walk dir {}", src.to_string_lossy()))?;
+        let target = dest.join(entry.path().strip_prefix(src)?);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)
+                .wrap_err_with(|| format!("could not create dir {}", target.to_string_lossy()))?;
+        } else if entry.file_type().is_symlink() {
+            copy_symlink(entry.path(), &target)?;
+        } else {
+            copy_verified(entry.path(), &target, verify_checksum, &mut |_| ())?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_symlink(src: &Path, dest: &Path) -> eyre::Result<()> {
+    let link_target = fs::read_link(src)
+        .wrap_err_with(|| format!("could not read link {}", src.to_string_lossy()))?;
+
+    #[cfg(unix)]
+    let res = std::os::unix::fs::symlink(&link_target, dest);
+    #[cfg(windows)]
+    let res = match src.is_dir() {
+        true => std::os::windows::fs::symlink_dir(&link_target, dest),
+        false => std::os::windows::fs::symlink_file(&link_target, dest),
+    };
+
+    res.wrap_err_with(|| format!("could not recreate link {} as {}", src.to_string_lossy(), dest.to_string_lossy()))
+}
+
+pub fn link_files(src: &Path, dest: &Path, verify_checksum: bool) -> eyre::Result<()> {
+    link_files_with_progress(src, dest, verify_checksum, &mut |_| ())
+}
+
+pub fn link_files_with_progress(src: &Path, dest: &Path, verify_checksum: bool, progress: &mut dyn FnMut(u64)) -> eyre::Result<()> {
+    create_parent(dest)?;
+
+    match fs::hard_link(src, dest) {
+        Ok(_) => Ok(()),
+        Err(e) if is_cross_device(&e) => {
+            info!("path {} is on another filesystem than {}, falling back to copy", 
+                src.to_string_lossy(), dest.to_string_lossy());
+            copy_verified(src, dest, verify_checksum, progress)
+        },
+        Err(e) => Err(e).wrap_err_with(|| 
+            format!("could not link path {} to {}", src.to_string_lossy(), dest.to_string_lossy())),
+    }
+}
+
+pub fn create_parent(path: &Path) -> eyre::Result<()> {
+    match path.parent() {
         Some(p) => fs::create_dir_all(p)?,
         None => (),
     };
 
-    fs::rename(src, dest).wrap_err_with(|| 
-        format!("could not move path {} to {}", src.to_string_lossy(), dest.to_string_lossy()))
+    Ok(())
+}
+
+fn is_cross_device(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(CROSS_DEVICE_ERROR)
+}
+
+pub fn copy_verified(src: &Path, dest: &Path, verify_checksum: bool, progress: &mut dyn FnMut(u64)) -> eyre::Result<()> {
+    let partial = partial_path(dest);
+
+    let res = copy_to_partial(src, &partial, verify_checksum, progress)
+        .and_then(|_| fs::rename(&partial, dest).wrap_err_with(|| 
+            format!("could not rename partial copy {} to {}", partial.to_string_lossy(), dest.to_string_lossy())));
+
+    if res.is_err() && partial.exists() {
+        if let Err(e) = fs::remove_file(&partial) {
+            info!("could not remove partial copy {}: {:?}", partial.to_string_lossy(), e);
+        }
+    }
+
+    res
+}
+
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(PARTIAL_EXT);
+    dest.with_file_name(name)
+}
+
+fn copy_to_partial(src: &Path, partial: &Path, verify_checksum: bool, progress: &mut dyn FnMut(u64)) -> eyre::Result<()> {
+    let mut src_file = File::open(src)
+        .wrap_err_with(|| format!("could not open source {}", src.to_string_lossy()))?;
+    let src_meta = src_file.metadata()?;
+
+    let mut writer = BufWriter::with_capacity(COPY_BUF_SIZE, File::create(partial)
+        .wrap_err_with(|| format!("could not create partial copy {}", partial.to_string_lossy()))?);
+    let mut buf = vec![0; COPY_BUF_SIZE];
+    let mut copied = 0;
+
+    loop {
+        let read = src_file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buf[..read])?;
+        copied += read as u64;
+        progress(copied);
+    }
+    writer.flush()?;
+
+    let partial_file = writer.into_inner().map_err(|e| e.into_error())?;
+    partial_file.sync_all()?;
+
+    let partial_len = partial_file.metadata()?.len();
+    if copied != src_meta.len() || partial_len != src_meta.len() {
+        return Err(eyre!("size mismatch when copying {}, expected {} bytes but got {}", 
+            src.to_string_lossy(), src_meta.len(), partial_len));
+    }
+
+    if verify_checksum && checksum(src)? != checksum(partial)? {
+        return Err(eyre!("checksum mismatch when copying {}", src.to_string_lossy()));
+    }
+
+    fs::set_permissions(partial, src_meta.permissions())?;
+    partial_file.set_modified(src_meta.modified()?)?;
+
+    Ok(())
+}
+
+fn checksum(path: &Path) -> eyre::Result<u32> {
+    let mut reader = BufReader::with_capacity(COPY_BUF_SIZE, File::open(path)?);
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; COPY_BUF_SIZE];
+
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hasher.finalize())
 }
 
 pub fn delete_dir(path: &Path) -> eyre::Result<()> {
     fs::remove_dir_all(path).wrap_err_with(|| 
         format!("could not remove all dirs from path {}", path.to_string_lossy()))
 }
+
+pub fn delete_empty_parents(path: &Path, stop_at: &[&Path]) {
+    for parent in path.ancestors().skip(1) {
+        if stop_at.contains(&parent) || fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> eyre::Result<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(eyre!("could not read free space of {}: {}", path.to_string_lossy(), io::Error::last_os_error()));
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(path: &Path) -> eyre::Result<u64> {
+    Err(eyre!("free space of {} can only be read on unix", path.to_string_lossy()))
+}
+
+// paths not created yet are on the filesystem of their closest existing parent
+#[cfg(unix)]
+pub fn same_filesystem(first: &Path, second: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| path.ancestors()
+        .find_map(|p| fs::metadata(p).ok())
+        .map(|data| data.dev());
+    match (device(first), device(second)) {
+        (Some(first), Some(second)) => first == second,
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn same_filesystem(first: &Path, second: &Path) -> bool {
+    canonicalize(first).components().next() == canonicalize(second).components().next()
+}
+
+pub fn canonicalize(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+
+    let normalized = normalize(path);
+    let mut missing = vec![];
+    let mut existing = normalized.as_path();
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return missing.into_iter().rev().fold(canonical, |acc, segment| acc.join(segment));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            },
+            _ => return normalized,
+        }
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => { normalized.pop(); },
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+#[derive(Debug)]
+pub struct PathMatcher {
+    patterns: Vec<(String, Regex)>,
+}
+
+impl PathMatcher {
+    pub fn new(patterns: &[String], match_descendants: bool) -> Self {
+        let suffix = if match_descendants { "(/.*)?$" } else { "$" };
+        let patterns = patterns.iter()
+            .filter(|p| !p.trim_end_matches('/').is_empty())
+            .map(|p| {
+                let pattern = p.trim_end_matches('/');
+                let regex = match Path::new(pattern).is_absolute() {
+                    true => format!("^{}{}", glob_to_regex(&canonicalize_glob_prefix(pattern)), suffix),
+                    false => format!("(^|/){}{}", glob_to_regex(pattern), suffix),
+                };
+                (p.clone(), Regex::new(&regex).unwrap())
+            })
+            .collect();
+
+        PathMatcher { patterns }
+    }
+
+    pub fn find_match(&self, path: &Path) -> Option<&str> {
+        let path = canonicalize(path).to_string_lossy().replace('\\', "/");
+        self.patterns.iter()
+            .find(|(_, regex)| regex.is_match(&path))
+            .map(|(pattern, _)| pattern.as_str())
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        self.find_match(path).is_some()
+    }
+}
+
+fn canonicalize_glob_prefix(pattern: &str) -> String {
+    let literal_end = pattern.find(['*', '?']).unwrap_or(pattern.len());
+    let prefix_end = match literal_end == pattern.len() {
+        true => literal_end,
+        false => pattern[..literal_end].rfind('/').unwrap_or(0),
+    };
+
+    let prefix = canonicalize(Path::new(&pattern[..prefix_end])).to_string_lossy().replace('\\', "/");
+    format!("{}{}", prefix.trim_end_matches('/'), &pattern[prefix_end..])
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            },
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '\\' => regex.push('/'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
@@ -8,7 +8,7 @@ use tracing::info;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::{config::Settings, search, download, command, moving, rename, db::DbClient, openapi::ApiDoc, error::Error};
+use crate::{config::Settings, archive, classify, images, library, search, download, command, moving, rename, trash, db::DbClient, openapi::ApiDoc, error::Error};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -47,9 +47,14 @@ fn api_router(settings: Arc<Settings>, db_client: DbClient) -> Router {
     Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.yml", ApiDoc::openapi()))
         .merge(search::router())
+        .merge(archive::router())
+        .merge(classify::router())
+        .merge(library::router())
         .merge(download::router())
         .merge(command::router())
         .merge(moving::router())
+        .merge(trash::router())
+        .merge(images::router(settings.clone()))
         .merge(rename::router(settings, db_client))
         .layer(cors_layer())
 }
\ No newline at end of file
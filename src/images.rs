@@ -0,0 +1,282 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::SystemTime};
+
+use axum::{extract::{Path as ReqPath, State}, http::header, response::IntoResponse, routing::get, Router};
+use eyre::eyre;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{config::Settings, error::Error, http};
+
+pub const IMAGES_PATH: &str = "/api/v1/images/";
+
+const FALLBACK_CONTENT_TYPE: &str = "application/octet-stream";
+const CACHE_CONTROL: &str = "public, max-age=604800";
+const PARTIAL_EXT: &str = "partial";
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CachedImage>,
+    total_bytes: u64,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct CachedImage {
+    size: u64,
+    last_used: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, key: &str) -> bool {
+        self.tick += 1;
+        match self.entries.get_mut(key) {
+            Some(image) => {
+                image.last_used = self.tick;
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: String, size: u64) {
+        self.tick += 1;
+        if let Some(old) = self.entries.insert(key, CachedImage { size, last_used: self.tick }) {
+            self.total_bytes -= old.size;
+        }
+        self.total_bytes += size;
+    }
+
+    // the image just stored is never evicted, even when it alone is over the limit
+    fn evict(&mut self, max_bytes: u64, keep: &str) -> Vec<String> {
+        let mut evicted = vec![];
+        while self.total_bytes > max_bytes {
+            let oldest = self.entries.iter()
+                .filter(|(key, _)| key.as_str() != keep)
+                .min_by_key(|(_, image)| image.last_used)
+                .map(|(key, _)| key.clone());
+
+            match oldest.and_then(|key| self.entries.remove_entry(&key)) {
+                Some((key, image)) => {
+                    self.total_bytes -= image.size;
+                    evicted.push(key);
+                },
+                None => break,
+            }
+        }
+        evicted
+    }
+}
+
+pub struct CachedBytes {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+pub struct ImageCache {
+    settings: Arc<Settings>,
+    client: reqwest::Client,
+    state: Mutex<CacheState>,
+}
+
+impl ImageCache {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        let state = load_state(Path::new(&settings.images.path));
+        info!("image cache holds {} images, {} bytes", state.entries.len(), state.total_bytes);
+
+        ImageCache {
+            settings,
+            client: reqwest::Client::new(),
+            state: Mutex::new(state),
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        Path::new(&self.settings.images.path).join(name)
+    }
+
+    pub async fn get(&self, key: &str) -> eyre::Result<Option<CachedBytes>> {
+        let url = match decode_key(key).filter(|url| is_proxied(&self.settings, url)) {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+
+        let name = file_name(&url);
+        let path = self.path(&name);
+        let cached = self.state.lock().unwrap().touch(&name);
+        if cached {
+            match tokio::fs::read(&path).await {
+                Ok(bytes) => return Ok(Some(to_cached(bytes))),
+                Err(e) => warn!("could not read cached image {}, fetching it again: {:?}", path.to_string_lossy(), e),
+            }
+        }
+
+        let resp = self.client.get(&url).send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let bytes = read_body(resp.error_for_status()?, self.settings.images.max_image_bytes).await?;
+
+        tokio::fs::create_dir_all(&self.settings.images.path).await?;
+        write_image(&path, &bytes).await?;
+        info!("cached image {} as {}", &url, &name);
+
+        let evicted = {
+            let mut state = self.state.lock().unwrap();
+            state.insert(name.clone(), bytes.len() as u64);
+            state.evict(self.settings.images.max_bytes, &name)
+        };
+        for old in evicted {
+            if let Err(e) = tokio::fs::remove_file(self.path(&old)).await {
+                warn!("could not remove evicted image {}: {:?}", &old, e);
+            }
+        }
+
+        Ok(Some(to_cached(bytes)))
+    }
+}
+
+// readers never see a half written image, concurrent fetches of the same image each use their own partial file
+async fn write_image(path: &Path, bytes: &[u8]) -> eyre::Result<()> {
+    let partial = path.with_extension(format!("{:08x}.{}", rand::random::<u32>(), PARTIAL_EXT));
+
+    let res = match tokio::fs::write(&partial, bytes).await {
+        Ok(_) => tokio::fs::rename(&partial, path).await,
+        Err(e) => Err(e),
+    };
+    if res.is_err() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+
+    Ok(res?)
+}
+
+// the body is read in chunks so an oversized response is dropped before it's all in memory
+pub async fn read_body(mut resp: reqwest::Response, max_bytes: u64) -> eyre::Result<Vec<u8>> {
+    let url = resp.url().to_string();
+    let too_large = || eyre!("image {} is larger than {} bytes", url, max_bytes);
+    if resp.content_length().map(|len| len > max_bytes).unwrap_or(false) {
+        return Err(too_large());
+    }
+
+    let mut bytes = vec![];
+    while let Some(chunk) = resp.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > max_bytes {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn to_cached(bytes: Vec<u8>) -> CachedBytes {
+    let content_type = infer::get(&bytes)
+        .map(|t| t.mime_type())
+        .unwrap_or(FALLBACK_CONTENT_TYPE)
+        .to_owned();
+    CachedBytes { content_type, bytes }
+}
+
+// images already on disk are ordered by modification time, so the oldest are evicted first
+fn load_state(dir: &Path) -> CacheState {
+    let mut state = CacheState::default();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return state,
+    };
+
+    let mut images: Vec<(SystemTime, String, u64)> = vec![];
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let data = match entry.metadata() {
+            Ok(data) if data.is_file() => data,
+            _ => continue,
+        };
+
+        if path.extension().map(|e| e == PARTIAL_EXT).unwrap_or(false) {
+            info!("removing partial image {} left by an interrupted write", path.to_string_lossy());
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("could not remove partial image {}: {:?}", path.to_string_lossy(), e);
+            }
+        } else if is_file_name(&name) {
+            images.push((data.modified().unwrap_or(SystemTime::UNIX_EPOCH), name, data.len()));
+        }
+    }
+    images.sort();
+
+    for (_, name, size) in images {
+        state.insert(name, size);
+    }
+    state
+}
+
+pub fn router(settings: Arc<Settings>) -> Router {
+    Router::new()
+        .route("/api/v1/images/:key", get(get_image))
+        .with_state(Arc::new(ImageCache::new(settings)))
+}
+
+#[utoipa::path(get, path = "/api/v1/images/{key}",
+    params(
+        ("key" = String, Path, description = "key of the image, as found in rewritten poster and fanart urls"),
+    ),
+    responses(
+        (status = 200, description = "Get an image of the metadata provider, fetching and caching it locally on first use", content_type = "image/jpeg"),
+        (status = 404, description = "Image key is invalid or the image doesn't exist")
+    )
+)]
+pub async fn get_image(State(cache): State<Arc<ImageCache>>, ReqPath(key): ReqPath<String>) -> http::Result<impl IntoResponse> {
+    info!("get_image request received for key {}", key);
+
+    match cache.get(&key).await? {
+        Some(image) => Ok(([(header::CONTENT_TYPE, image.content_type), (header::CACHE_CONTROL, CACHE_CONTROL.to_owned())], image.bytes)),
+        None => Err(Error::NotFound),
+    }
+}
+
+fn is_proxied(settings: &Settings, url: &str) -> bool {
+    let online = &settings.online;
+    [&online.poster_base, &online.fanart_base].iter()
+        .any(|base| !base.is_empty() && url.starts_with(base.as_str()))
+}
+
+pub fn proxy_url(settings: &Settings, url: &str) -> String {
+    if !settings.images.proxy || !is_proxied(settings, url) {
+        return url.to_owned();
+    }
+    format!("{}{}{}", &settings.images.public_base, IMAGES_PATH, encode_key(url))
+}
+
+// rewritten urls sent back by clients point to this service, the provider url is in the key
+pub fn original_url(url: &str) -> String {
+    url.split_once(IMAGES_PATH)
+        .and_then(|(_, key)| decode_key(key))
+        .unwrap_or_else(|| url.to_owned())
+}
+
+pub fn encode_key(url: &str) -> String {
+    url.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+// keys differing only in hex case decode to the same url, and so to the same file
+fn decode_key(key: &str) -> Option<String> {
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let bytes: Option<Vec<u8>> = key.as_bytes().chunks(2)
+        .map(|pair| std::str::from_utf8(pair).ok()
+            .filter(|p| p.len() == 2)
+            .and_then(|p| u8::from_str_radix(p, 16).ok()))
+        .collect();
+    bytes.and_then(|b| String::from_utf8(b).ok())
+}
+
+// keys grow with the url, so files are named by a fixed length digest of it instead
+pub fn file_name(url: &str) -> String {
+    Sha256::digest(url.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn is_file_name(name: &str) -> bool {
+    name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+}
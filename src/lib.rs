@@ -1,14 +1,22 @@
+pub mod archive;
+pub mod classify;
 pub mod command;
 pub mod config;
 pub mod db;
 pub mod download;
+pub mod episode;
 pub mod files;
 pub mod error;
 pub mod http;
+pub mod images;
+pub mod library;
 pub mod mongo;
 pub mod moving;
+pub mod quality;
 pub mod rename;
+pub mod roots;
 pub mod tmdb;
+pub mod trash;
 pub mod search;
 pub mod openapi;
 pub mod tests;
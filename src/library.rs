@@ -0,0 +1,522 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{extract::Query, routing::get, Extension, Json, Router};
+use chrono::{NaiveDate, Utc};
+use eyre::{eyre, Context};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    config::Settings,
+    episode::EpisodeParser,
+    files,
+    http::{self, ApiContext},
+    moving::{release, subs::SubsNamer},
+    quality::{MediaQuality, QualityParser},
+    rename::{external::{ExternalEpisode, ExternalEpisodeSearcher, ExternalSearcher}, name::NameGenerator, MediaFileType},
+    roots,
+    tmdb::TmdbAPI,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct LibraryMedia {
+    pub path: String,
+    pub name: String,
+    pub root: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub files: usize,
+    #[serde(rename(serialize = "totalSize", deserialize = "totalSize"))]
+    #[schema(rename = "totalSize")]
+    pub total_size: u64,
+    pub qualities: Vec<MediaQuality>,
+    #[serde(rename(serialize = "subtitleLanguages", deserialize = "subtitleLanguages"))]
+    #[schema(rename = "subtitleLanguages")]
+    pub subtitle_languages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seasons: Vec<LibrarySeason>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct LibrarySeason {
+    pub season: Option<u32>,
+    pub episodes: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LibraryPage {
+    pub items: Vec<LibraryMedia>,
+    pub total: usize,
+    pub page: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LibraryDuplicates {
+    pub folders: Vec<DuplicateGroup>,
+    pub episodes: Vec<DuplicateGroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct DuplicateGroup {
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    #[schema(rename = "type")]
+    pub media_type: MediaFileType,
+    pub title: String,
+    pub year: Option<i32>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    pub copies: Vec<DuplicateCopy>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct DuplicateCopy {
+    pub path: String,
+    pub size: u64,
+    pub qualities: Vec<MediaQuality>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct MissingEpisodes {
+    pub path: String,
+    pub title: String,
+    pub year: Option<i32>,
+    #[serde(rename(serialize = "providerId", deserialize = "providerId"))]
+    #[schema(rename = "providerId")]
+    pub provider_id: Option<i32>,
+    pub missing: Vec<LibrarySeason>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MissingEpisodesParams {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryParams {
+    pub name: Option<String>,
+    pub year: Option<i32>,
+    pub quality: Option<String>,
+    pub page: Option<usize>,
+    pub size: Option<usize>,
+}
+
+struct LibraryEntry {
+    root: String,
+    path: PathBuf,
+    name: String,
+    title: String,
+    year: Option<i32>,
+}
+
+enum LibraryFileKind {
+    Video { quality: MediaQuality, season: Option<u32>, episode: Option<u32> },
+    Subtitle { language: Option<String> },
+    Other,
+}
+
+struct LibraryFile {
+    path: PathBuf,
+    size: u64,
+    kind: LibraryFileKind,
+}
+
+pub struct LibraryScanner {
+    settings: Arc<Settings>,
+    generator: NameGenerator,
+    quality_parser: QualityParser,
+    episode_parser: EpisodeParser,
+    subs_namer: SubsNamer,
+    season_dir_regex: Regex,
+}
+
+impl LibraryScanner {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        LibraryScanner {
+            generator: NameGenerator::new(settings.clone()),
+            quality_parser: QualityParser::new(),
+            episode_parser: EpisodeParser::new(),
+            subs_namer: SubsNamer::new(),
+            season_dir_regex: Regex::new(r"(?i)^(?:season|series|s)[ ._-]?(\d{1,2})$").unwrap(),
+            settings,
+        }
+    }
+
+    fn entries(&self, media_type: MediaFileType) -> eyre::Result<Vec<LibraryEntry>> {
+        let mut entries = vec![];
+        for root in roots::library_roots(&self.settings, media_type) {
+            let root_path = Path::new(&root.path);
+            if !root_path.is_dir() {
+                continue;
+            }
+
+            for dir in fs::read_dir(root_path).wrap_err_with(|| format!("could not list library root {}", &root.path))? {
+                let path = dir?.path();
+                if !path.is_dir() {
+                    continue;
+                }
+
+                let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let base_info = self.generator.generate_base_info(name.clone());
+                entries.push(LibraryEntry {
+                    root: root.name.clone(),
+                    title: base_info.name().to_owned(),
+                    year: base_info.year(),
+                    path,
+                    name,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()).then(a.year.cmp(&b.year)));
+        Ok(entries)
+    }
+
+    fn entries_named(&self, media_type: MediaFileType, name: Option<&str>) -> eyre::Result<Vec<LibraryEntry>> {
+        let name = name.map(|n| n.to_lowercase());
+        Ok(self.entries(media_type)?.into_iter()
+            .filter(|e| name.as_ref().map(|n| e.title.to_lowercase().contains(n) || e.name.to_lowercase().contains(n)).unwrap_or(true))
+            .collect())
+    }
+
+    fn scan(&self, entry: &LibraryEntry, media_type: MediaFileType) -> eyre::Result<Vec<LibraryFile>> {
+        let mut scanned = vec![];
+        for file in files::walk_files(&entry.path, self.settings.library.max_depth)? {
+            let relative = file.path().strip_prefix(&entry.path).unwrap_or(file.path());
+            let ext = file.path().extension().unwrap_or_default();
+
+            let kind = if self.settings.mv.subs_ext.iter().any(|e| OsStr::new(e).eq_ignore_ascii_case(ext)) {
+                LibraryFileKind::Subtitle { language: self.subs_namer.detect(file.path()).language }
+            } else if release::is_video(&self.settings, file.path()) {
+                let (season, episode) = match media_type {
+                    MediaFileType::TV => self.episode_parser.parse(relative),
+                    _ => (None, None),
+                };
+                LibraryFileKind::Video {
+                    quality: self.quality_parser.parse_file(&Path::new(&entry.name).join(relative)),
+                    season: season.or_else(|| episode.and_then(|_| self.season_from_dirs(relative))),
+                    episode,
+                }
+            } else {
+                LibraryFileKind::Other
+            };
+
+            scanned.push(LibraryFile {
+                size: file.metadata().map(|m| m.len()).unwrap_or(0),
+                path: file.into_path(),
+                kind,
+            });
+        }
+        Ok(scanned)
+    }
+
+    fn describe(&self, entry: &LibraryEntry, media_type: MediaFileType) -> eyre::Result<LibraryMedia> {
+        let scanned = self.scan(entry, media_type)?;
+        let mut qualities = vec![];
+        let mut languages = BTreeSet::new();
+        let mut seasons: BTreeMap<Option<u32>, BTreeSet<u32>> = BTreeMap::new();
+
+        for file in &scanned {
+            match &file.kind {
+                LibraryFileKind::Video { quality, season, episode } => {
+                    if !qualities.contains(quality) {
+                        qualities.push(quality.clone());
+                    }
+                    if let Some(episode) = episode {
+                        seasons.entry(*season).or_default().insert(*episode);
+                    }
+                },
+                LibraryFileKind::Subtitle { language } => languages.extend(language.clone()),
+                LibraryFileKind::Other => (),
+            }
+        }
+
+        Ok(LibraryMedia {
+            path: entry.path.to_string_lossy().into_owned(),
+            name: entry.name.clone(),
+            root: entry.root.clone(),
+            title: entry.title.clone(),
+            year: entry.year,
+            files: scanned.len(),
+            total_size: scanned.iter().map(|f| f.size).sum(),
+            qualities,
+            subtitle_languages: languages.into_iter().collect(),
+            seasons: seasons.into_iter()
+                .map(|(season, episodes)| LibrarySeason { season, episodes: episodes.into_iter().collect() })
+                .collect(),
+        })
+    }
+
+    // matches file names first, so only the files claiming the resolution get their content sniffed
+    fn has_resolution(&self, entry: &LibraryEntry, resolution: &str) -> eyre::Result<bool> {
+        for file in files::walk_files(&entry.path, self.settings.library.max_depth)? {
+            let relative = file.path().strip_prefix(&entry.path).unwrap_or(file.path());
+            let quality = self.quality_parser.parse_file(&Path::new(&entry.name).join(relative));
+            if quality.resolution.map(|r| r.eq_ignore_ascii_case(resolution)).unwrap_or(false) && release::is_video(&self.settings, file.path()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn season_from_dirs(&self, relative: &Path) -> Option<u32> {
+        relative.parent()?.iter()
+            .find_map(|d| self.season_dir_regex.captures(&d.to_string_lossy()).and_then(|c| c[1].parse().ok()))
+    }
+
+    pub fn page(&self, media_type: MediaFileType, params: &LibraryParams) -> eyre::Result<LibraryPage> {
+        let mut entries: Vec<LibraryEntry> = self.entries_named(media_type, params.name.as_deref())?.into_iter()
+            .filter(|e| params.year.is_none() || e.year == params.year)
+            .collect();
+
+        // only the requested page gets fully described, the quality filter just needs one matching video per entry
+        if let Some(quality) = &params.quality {
+            let mut matching = vec![];
+            for entry in entries {
+                if self.has_resolution(&entry, quality)? {
+                    matching.push(entry);
+                }
+            }
+            entries = matching;
+        }
+
+        let page = params.page.unwrap_or(1).max(1);
+        let size = params.size.unwrap_or(self.settings.library.page_size).clamp(1, self.settings.library.max_page_size);
+        let skip = (page - 1).saturating_mul(size);
+
+        let items = entries.iter().skip(skip).take(size)
+            .map(|e| self.describe(e, media_type))
+            .collect::<eyre::Result<Vec<LibraryMedia>>>()?;
+        let total = entries.len();
+
+        Ok(LibraryPage { items, total, page, size })
+    }
+
+    pub fn duplicates(&self) -> eyre::Result<LibraryDuplicates> {
+        let mut duplicates = LibraryDuplicates { folders: vec![], episodes: vec![] };
+
+        for media_type in [MediaFileType::MOVIE, MediaFileType::TV] {
+            let entries = self.entries(media_type)?;
+
+            let mut by_title: BTreeMap<(String, Option<i32>), Vec<&LibraryEntry>> = BTreeMap::new();
+            for entry in &entries {
+                by_title.entry((self.generator.comparable_name(&entry.title), entry.year)).or_default().push(entry);
+            }
+            for copies in by_title.into_values().filter(|c| c.len() > 1) {
+                let mut group = DuplicateGroup {
+                    media_type,
+                    title: copies[0].title.clone(),
+                    year: copies[0].year,
+                    season: None,
+                    episode: None,
+                    copies: vec![],
+                };
+                for copy in copies {
+                    let media = self.describe(copy, media_type)?;
+                    group.copies.push(DuplicateCopy { path: media.path, size: media.total_size, qualities: media.qualities });
+                }
+                duplicates.folders.push(group);
+            }
+
+            if media_type != MediaFileType::TV {
+                continue;
+            }
+            for entry in &entries {
+                let mut by_episode: BTreeMap<(Option<u32>, u32), Vec<DuplicateCopy>> = BTreeMap::new();
+                for file in self.scan(entry, media_type)? {
+                    if let LibraryFileKind::Video { quality, season, episode: Some(episode) } = file.kind {
+                        by_episode.entry((season, episode)).or_default().push(DuplicateCopy {
+                            path: file.path.to_string_lossy().into_owned(),
+                            size: file.size,
+                            qualities: vec![quality],
+                        });
+                    }
+                }
+                duplicates.episodes.extend(by_episode.into_iter()
+                    .filter(|(_, copies)| copies.len() > 1)
+                    .map(|((season, episode), copies)| DuplicateGroup {
+                        media_type,
+                        title: entry.title.clone(),
+                        year: entry.year,
+                        season,
+                        episode: Some(episode),
+                        copies,
+                    }));
+            }
+        }
+
+        Ok(duplicates)
+    }
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/v1/library/movies", get(library_movies))
+        .route("/api/v1/library/tv", get(library_tv))
+        .route("/api/v1/library/duplicates", get(library_duplicates))
+        .route("/api/v1/library/tv/missing", get(library_missing_episodes))
+}
+
+#[utoipa::path(get, path = "/api/v1/library/movies",
+    params(
+        ("name" = Option<String>, Query, description = "only movies whose title or folder contains this text"),
+        ("year" = Option<i32>, Query, description = "only movies released in this year"),
+        ("quality" = Option<String>, Query, description = "only movies having a video of this resolution, like 1080p"),
+        ("page" = Option<usize>, Query, description = "page to retrieve, starting from 1"),
+        ("size" = Option<usize>, Query, description = "movies per page"),
+    ),
+    responses(
+        (status = 200, description = "List movies found in the library roots", body = LibraryPage)
+    )
+)]
+pub async fn library_movies(ctx: Extension<ApiContext>, Query(params): Query<LibraryParams>) -> http::Result<Json<LibraryPage>> {
+    info!("library_movies request received with params {:?}", params);
+
+    Ok(Json(library_page(ctx.settings.clone(), MediaFileType::MOVIE, params).await?))
+}
+
+#[utoipa::path(get, path = "/api/v1/library/tv",
+    params(
+        ("name" = Option<String>, Query, description = "only shows whose title or folder contains this text"),
+        ("year" = Option<i32>, Query, description = "only shows first aired in this year"),
+        ("quality" = Option<String>, Query, description = "only shows having a video of this resolution, like 1080p"),
+        ("page" = Option<usize>, Query, description = "page to retrieve, starting from 1"),
+        ("size" = Option<usize>, Query, description = "shows per page"),
+    ),
+    responses(
+        (status = 200, description = "List TV shows found in the library roots, with the seasons and episodes present", body = LibraryPage)
+    )
+)]
+pub async fn library_tv(ctx: Extension<ApiContext>, Query(params): Query<LibraryParams>) -> http::Result<Json<LibraryPage>> {
+    info!("library_tv request received with params {:?}", params);
+
+    Ok(Json(library_page(ctx.settings.clone(), MediaFileType::TV, params).await?))
+}
+
+#[utoipa::path(get, path = "/api/v1/library/duplicates",
+    responses(
+        (status = 200, description = "Report library folders with the same title and year, and shows holding an episode more than once", body = LibraryDuplicates)
+    )
+)]
+pub async fn library_duplicates(ctx: Extension<ApiContext>) -> http::Result<Json<LibraryDuplicates>> {
+    info!("library_duplicates request received");
+
+    let settings = ctx.settings.clone();
+    let duplicates = tokio::task::spawn_blocking(move || LibraryScanner::new(settings).duplicates()).await
+        .wrap_err("library duplicates task could not complete")??;
+
+    Ok(Json(duplicates))
+}
+
+#[utoipa::path(get, path = "/api/v1/library/tv/missing",
+    params(
+        ("name" = Option<String>, Query, description = "only check shows whose title or folder contains this text"),
+    ),
+    responses(
+        (status = 200, description = "Report episodes the metadata provider lists but are not in the library, per show and season", body = [MissingEpisodes])
+    )
+)]
+pub async fn library_missing_episodes(ctx: Extension<ApiContext>, Query(params): Query<MissingEpisodesParams>) -> http::Result<Json<Vec<MissingEpisodes>>> {
+    info!("library_missing_episodes request received with params {:?}", params);
+
+    let tmdb = TmdbAPI::new(ctx.settings.clone());
+    Ok(Json(find_missing_episodes(ctx.settings.clone(), &tmdb, &tmdb, params.name).await?))
+}
+
+pub async fn find_missing_episodes(settings: Arc<Settings>, searcher: &dyn ExternalSearcher, 
+        episode_searcher: &dyn ExternalEpisodeSearcher, name: Option<String>) -> eyre::Result<Vec<MissingEpisodes>> {
+    let scan_settings = settings.clone();
+    let shows = tokio::task::spawn_blocking(move || -> eyre::Result<Vec<LibraryMedia>> {
+        let scanner = LibraryScanner::new(scan_settings);
+        scanner.entries_named(MediaFileType::TV, name.as_deref())?.iter()
+            .map(|e| scanner.describe(e, MediaFileType::TV))
+            .collect()
+    }).await.wrap_err("library scan task could not complete")??;
+
+    let generator = NameGenerator::new(settings.clone());
+    let today = Utc::now().date_naive();
+    let mut report = vec![];
+
+    for show in shows {
+        let mut missing = MissingEpisodes {
+            path: show.path.clone(),
+            title: show.title.clone(),
+            year: show.year,
+            provider_id: None,
+            missing: vec![],
+            error: None,
+        };
+
+        match provider_episodes(&generator, searcher, episode_searcher, &show).await {
+            Ok((id, episodes)) => {
+                let mut by_season: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+                for e in episodes.into_iter().filter(|e| is_expected(&settings, e, today) && !is_on_disk(&show, e)) {
+                    by_season.entry(e.season).or_default().insert(e.episode);
+                }
+                missing.provider_id = Some(id);
+                missing.missing = by_season.into_iter()
+                    .map(|(season, episodes)| LibrarySeason { season: Some(season), episodes: episodes.into_iter().collect() })
+                    .collect();
+            },
+            Err(e) => {
+                warn!("could not get episodes of show {} from metadata provider: {:?}", &show.title, e);
+                missing.error = Some(e.to_string());
+            },
+        }
+
+        if !missing.missing.is_empty() || missing.error.is_some() {
+            report.push(missing);
+        }
+    }
+
+    Ok(report)
+}
+
+async fn provider_episodes(generator: &NameGenerator, searcher: &dyn ExternalSearcher, 
+        episode_searcher: &dyn ExternalEpisodeSearcher, show: &LibraryMedia) -> eyre::Result<(i32, Vec<ExternalEpisode>)> {
+    let results = searcher.search_tv(&show.title, show.year).await?;
+    let title = generator.comparable_name(&show.title);
+    // a year is only compared when both sides know it
+    let found = results.iter()
+        .filter(|r| generator.comparable_name(&r.title) == title)
+        .find(|r| match (show.year, r.date.get(..4).and_then(|y| y.parse::<i32>().ok())) {
+            (Some(year), Some(provider_year)) => year == provider_year,
+            _ => true,
+        })
+        .ok_or_else(|| eyre!("metadata provider has no show matching {}{}", &show.title,
+            show.year.map(|y| format!(" ({})", y)).unwrap_or_default()))?;
+
+    Ok((found.id, episode_searcher.tv_episodes(found.id).await?))
+}
+
+fn is_expected(settings: &Settings, episode: &ExternalEpisode, today: NaiveDate) -> bool {
+    if settings.library.ignore_specials && episode.season == 0 {
+        return false;
+    }
+
+    let aired = episode.air_date.as_ref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .map(|d| d <= today)
+        .unwrap_or(false);
+    aired || !settings.library.ignore_unaired
+}
+
+// episodes whose season couldn't be read from disk count for any season
+fn is_on_disk(show: &LibraryMedia, episode: &ExternalEpisode) -> bool {
+    show.seasons.iter()
+        .any(|s| (s.season == Some(episode.season) || s.season.is_none()) && s.episodes.contains(&episode.episode))
+}
+
+async fn library_page(settings: Arc<Settings>, media_type: MediaFileType, params: LibraryParams) -> eyre::Result<LibraryPage> {
+    tokio::task::spawn_blocking(move || LibraryScanner::new(settings).page(media_type, &params)).await
+        .wrap_err("library scan task could not complete")?
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use commander::{
-    http, mongo::MongoDbWrapper, db::DbClient, config::Settings,
+    http, mongo::MongoDbWrapper, db::DbClient, config::Settings, moving::job, trash,
 };
 use eyre::Result;
 use mongodb::Client;
@@ -14,6 +14,8 @@ async fn main() -> Result<()> {
     let settings = Arc::new(init_config::<Settings>("config/settings", "CMDR")?);
     let client = Client::with_uri_str(&settings.mongodb.connection_url).await?;
     let db_client = DbClient::new(Arc::new(MongoDbWrapper::new(client, settings.clone())));
+    job::fail_interrupted_jobs(&db_client).await?;
+    trash::spawn_purge_task(settings.clone());
 
     http::serve(settings, db_client).await?;
 
@@ -2,12 +2,14 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
+use eyre::eyre;
 
 use futures::TryStreamExt;
-use mongodb::{bson::{DateTime, doc, Bson, Document}, Client};
+use mongodb::{bson::{DateTime, doc, Bson, Document}, Client, options::{ReplaceOptions, FindOptions}};
 use serde::{Serialize, Deserialize};
+use tracing::warn;
 
-use crate::{download::{DownloadCacheRepo, DownloadedMedia}, config::Settings, rename::{cache::{OnlineCacheRepo, OnlineCacheItem}, MediaFileType, name::BaseInfo}};
+use crate::{download::{DownloadCacheRepo, DownloadedMedia}, config::Settings, rename::{cache::{OnlineCacheRepo, OnlineCacheItem}, MediaActor, MediaFileType, name::BaseInfo}, moving::{job::{MoveJobRepo, MoveJob, MoveJobStatus}, journal::{MoveJournalRepo, MoveJournalEntry}, plan::PlannedMove, ImportMode}, trash::TrashItem};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct MongoDownloadedMedia {
@@ -44,10 +46,14 @@ struct MongoOnlineCacheItem {
     search_year: Option<i32>,
     #[serde(rename(serialize = "coverPath", deserialize = "coverPath"))]
     cover_path: String,
+    #[serde(default, rename(serialize = "fanartPath", deserialize = "fanartPath"))]
+    fanart_path: String,
     title: String,
     date: DateTime,
     description: String,
     cast: Vec<String>,
+    #[serde(default)]
+    actors: Vec<MediaActor>,
     #[serde(rename(serialize = "mediaType", deserialize = "mediaType"))]
     media_type: String,
 }
@@ -58,10 +64,12 @@ impl Into<OnlineCacheItem> for MongoOnlineCacheItem {
             search_name: self.search_name, 
             search_year: self.search_year,
             cover_path: self.cover_path, 
+            fanart_path: self.fanart_path, 
             title: self.title, 
             date: self.date.timestamp_millis(), 
             description: self.description, 
             cast: self.cast, 
+            actors: self.actors, 
             media_type: self.media_type.parse::<MediaFileType>().unwrap()
         }
     }
@@ -73,15 +81,135 @@ impl From<OnlineCacheItem> for MongoOnlineCacheItem {
             search_name: i.search_name,
             search_year: i.search_year,
             cover_path: i.cover_path,
+            fanart_path: i.fanart_path,
             title: i.title,
             date: DateTime::from_millis(i.date),
             description: i.description,
             cast: i.cast,
+            actors: i.actors,
             media_type: i.media_type.to_string(),
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct MongoMoveJob {
+    #[serde(rename(serialize = "_id", deserialize = "_id"))]
+    id: String,
+    #[serde(rename(serialize = "mediaPath", deserialize = "mediaPath"))]
+    media_path: String,
+    status: String,
+    #[serde(rename(serialize = "bytesDone", deserialize = "bytesDone"))]
+    bytes_done: i64,
+    #[serde(rename(serialize = "bytesTotal", deserialize = "bytesTotal"))]
+    bytes_total: i64,
+    #[serde(rename(serialize = "currentFile", deserialize = "currentFile"))]
+    current_file: Option<String>,
+    errors: Vec<String>,
+    #[serde(rename(serialize = "journalId", deserialize = "journalId"))]
+    journal_id: Option<String>,
+    created: DateTime,
+    updated: DateTime,
+}
+
+impl TryFrom<MongoMoveJob> for MoveJob {
+    type Error = eyre::Report;
+
+    fn try_from(j: MongoMoveJob) -> Result<Self, Self::Error> {
+        let status = j.status.parse::<MoveJobStatus>()
+            .map_err(|_| eyre!("unknown status {} of move job {}", &j.status, &j.id))?;
+
+        Ok(MoveJob {
+            id: j.id,
+            media_path: j.media_path,
+            status,
+            bytes_done: j.bytes_done as u64,
+            bytes_total: j.bytes_total as u64,
+            current_file: j.current_file,
+            errors: j.errors,
+            journal_id: j.journal_id,
+            created: j.created.timestamp_millis(),
+            updated: j.updated.timestamp_millis(),
+        })
+    }
+}
+
+impl From<&MoveJob> for MongoMoveJob {
+    fn from(j: &MoveJob) -> Self {
+        MongoMoveJob {
+            id: j.id.clone(),
+            media_path: j.media_path.clone(),
+            status: j.status.to_string(),
+            bytes_done: j.bytes_done as i64,
+            bytes_total: j.bytes_total as i64,
+            current_file: j.current_file.clone(),
+            errors: j.errors.clone(),
+            journal_id: j.journal_id.clone(),
+            created: DateTime::from_millis(j.created),
+            updated: DateTime::from_millis(j.updated),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MongoMoveJournalEntry {
+    #[serde(rename(serialize = "_id", deserialize = "_id"))]
+    id: String,
+    #[serde(rename(serialize = "mediaPath", deserialize = "mediaPath"))]
+    media_path: String,
+    moves: Vec<PlannedMove>,
+    #[serde(default)]
+    trashed: Vec<TrashItem>,
+    #[serde(default)]
+    replaced: Vec<TrashItem>,
+    #[serde(default)]
+    rewritten: Vec<TrashItem>,
+    #[serde(default, rename(serialize = "createdFiles", deserialize = "createdFiles"))]
+    created_files: Vec<String>,
+    #[serde(default, rename(serialize = "importMode", deserialize = "importMode"))]
+    import_mode: ImportMode,
+    undone: bool,
+    #[serde(default)]
+    cleaned: bool,
+    created: DateTime,
+}
+
+impl From<MongoMoveJournalEntry> for MoveJournalEntry {
+    fn from(e: MongoMoveJournalEntry) -> Self {
+        MoveJournalEntry {
+            id: e.id,
+            media_path: e.media_path,
+            moves: e.moves,
+            trashed: e.trashed,
+            replaced: e.replaced,
+            rewritten: e.rewritten,
+            created_files: e.created_files,
+            import_mode: e.import_mode,
+            undone: e.undone,
+            cleaned: e.cleaned,
+            created: e.created.timestamp_millis(),
+        }
+    }
+}
+
+impl From<&MoveJournalEntry> for MongoMoveJournalEntry {
+    fn from(e: &MoveJournalEntry) -> Self {
+        MongoMoveJournalEntry {
+            id: e.id.clone(),
+            media_path: e.media_path.clone(),
+            moves: e.moves.clone(),
+            trashed: e.trashed.clone(),
+            replaced: e.replaced.clone(),
+            rewritten: e.rewritten.clone(),
+            created_files: e.created_files.clone(),
+            import_mode: e.import_mode,
+            undone: e.undone,
+            cleaned: e.cleaned,
+            created: DateTime::from_millis(e.created),
+        }
+    }
+}
+
 impl From<MediaFileType> for Bson {
     fn from(value: MediaFileType) -> Self {
         Bson::String(value.to_string())
@@ -177,4 +305,110 @@ impl OnlineCacheRepo for MongoDbWrapper {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl MoveJobRepo for MongoDbWrapper {
+    async fn save_job(&self, job: &MoveJob) -> eyre::Result<()> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJob>(&self.settings.mongodb.move_job_collection);
+
+        let options = ReplaceOptions::builder().upsert(true).build();
+        col.replace_one(doc! { "_id": &job.id }, MongoMoveJob::from(job), options).await?;
+
+        Ok(())
+    }
+
+    async fn find_job(&self, id: &str) -> eyre::Result<Option<MoveJob>> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJob>(&self.settings.mongodb.move_job_collection);
+
+        let job = col.find_one(doc! { "_id": id }, None).await?;
+
+        job.map(MoveJob::try_from).transpose()
+    }
+
+    async fn find_jobs(&self, skip: u64, limit: u64) -> eyre::Result<Vec<MoveJob>> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJob>(&self.settings.mongodb.move_job_collection);
+
+        let options = FindOptions::builder()
+            .sort(doc! { "created": -1 })
+            .skip(skip)
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX))
+            .build();
+        let mut cursor = col.find(None, options).await?;
+
+        let mut jobs = vec![];
+        while let Some(job) = cursor.try_next().await? {
+            match MoveJob::try_from(job) {
+                Ok(job) => jobs.push(job),
+                Err(e) => warn!("skipping unreadable move job: {:?}", e),
+            }
+        }
+
+        Ok(jobs)
+    }
+
+    async fn count_jobs(&self) -> eyre::Result<u64> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJob>(&self.settings.mongodb.move_job_collection);
+
+        Ok(col.count_documents(None, None).await?)
+    }
+
+    async fn find_unfinished_jobs(&self) -> eyre::Result<Vec<MoveJob>> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJob>(&self.settings.mongodb.move_job_collection);
+
+        let unfinished = [MoveJobStatus::QUEUED, MoveJobStatus::RUNNING].map(|s| s.to_string());
+        let mut cursor = col.find(doc! { "status": { "$in": unfinished.to_vec() } }, None).await?;
+
+        let mut jobs = vec![];
+        while let Some(job) = cursor.try_next().await? {
+            match MoveJob::try_from(job) {
+                Ok(job) => jobs.push(job),
+                Err(e) => warn!("skipping unreadable move job: {:?}", e),
+            }
+        }
+
+        Ok(jobs)
+    }
+}
+
+#[async_trait]
+impl MoveJournalRepo for MongoDbWrapper {
+    async fn save_entry(&self, entry: &MoveJournalEntry) -> eyre::Result<()> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJournalEntry>(&self.settings.mongodb.move_journal_collection);
+
+        let options = ReplaceOptions::builder().upsert(true).build();
+        col.replace_one(doc! { "_id": &entry.id }, MongoMoveJournalEntry::from(entry), options).await?;
+
+        Ok(())
+    }
+
+    async fn find_entry(&self, id: &str) -> eyre::Result<Option<MoveJournalEntry>> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJournalEntry>(&self.settings.mongodb.move_journal_collection);
+
+        let entry = col.find_one(doc! { "_id": id }, None).await?;
+
+        Ok(entry.map(|e| e.into()))
+    }
+
+    async fn find_entries_by_media_path(&self, media_path: &str) -> eyre::Result<Vec<MoveJournalEntry>> {
+        let db = self.client.database(&self.settings.mongodb.database);
+        let col = db.collection::<MongoMoveJournalEntry>(&self.settings.mongodb.move_journal_collection);
+
+        let options = FindOptions::builder().sort(doc! { "created": -1 }).build();
+        let mut cursor = col.find(doc! { "mediaPath": media_path }, options).await?;
+
+        let mut entries = vec![];
+        while let Some(entry) = cursor.try_next().await? {
+            entries.push(entry.into());
+        }
+
+        Ok(entries)
+    }
+}
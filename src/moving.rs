@@ -1,8 +1,9 @@
-use std::{ffi::OsStr, path::Path, sync::Arc};
+use std::{cmp::max, collections::HashSet, fs, path::{Path, PathBuf}, sync::Arc};
 
-use axum::{extract::State, routing::post, Extension, Json, Router};
+use axum::{extract::{Path as ReqPath, Query, State}, routing::{get, post}, Extension, Json, Router};
 
-use eyre::eyre;
+use eyre::{eyre, Context};
+use futures::{stream, StreamExt};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
@@ -10,13 +11,34 @@ use utoipa::ToSchema;
 use walkdir::DirEntry;
 
 use crate::{
-    config::Settings, files, http::ApiContext, rename::MediaFileType, search::MediaFileGroup,
+    classify, config::Settings, db::DbClient, error::Error, files::{self, PathMatcher}, http::{self, ApiContext}, quality::QualityParser, rename::{MediaDescription, MediaFileType}, roots, search::MediaFileGroup, trash,
 };
 
+use self::{plan::{MediaMovePlan, PlannedMove}, job::{MoveJob, MoveJobPage, MoveJobParams}, journal::{MoveJournalEntry, MoveUndoResult}, conflict::ConflictStrategy, subs::SubsNamer,
+    metadata::MediaMetadata,
+    release::{ReleaseClassifier, ReleaseFileAction, ReleaseFileKind, EXTRAS_DIR}};
+
+pub mod conflict;
+pub mod job;
+pub mod journal;
+pub mod metadata;
+pub mod plan;
+pub mod release;
+pub mod subs;
+pub mod subs_import;
+pub mod validation;
+
 pub const SUBS_DIR: &str = "Subs";
 pub const EPISODE_SEGMENT_REGEX: &str = r".*[eE](\d{1,2}).*";
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub enum ImportMode {
+    #[default]
+    MOVE,
+    HARDLINK,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
 pub struct MediaMoveReq {
     #[serde(rename(serialize = "fileGroup", deserialize = "fileGroup"))]
     #[schema(rename = "fileGroup")]
@@ -24,92 +46,414 @@ pub struct MediaMoveReq {
     #[serde(rename(serialize = "type", deserialize = "type"))]
     #[schema(rename = "type")]
     pub media_type: MediaFileType,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, rename(serialize = "onConflict", deserialize = "onConflict"))]
+    #[schema(rename = "onConflict")]
+    pub on_conflict: ConflictStrategy,
+    #[serde(default, rename(serialize = "importMode", deserialize = "importMode"))]
+    #[schema(rename = "importMode")]
+    pub import_mode: Option<ImportMode>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub description: Option<MediaDescription>,
+}
+
+impl MediaMoveReq {
+    fn into_parts(self) -> (MediaFileGroup, Option<MediaDescription>) {
+        let mut file_group = self.file_group;
+        if let Some(name) = self.name {
+            file_group.name = name;
+        }
+        (file_group, self.description)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
-pub struct MediaMoveError {
+pub struct MediaMoveResult {
     #[serde(rename(serialize = "mediaPath", deserialize = "mediaPath"))]
     #[schema(rename = "mediaPath")]
     pub media_path: String,
-    pub error: String,
+    pub success: bool,
+    pub error: Option<String>,
+    #[serde(rename(serialize = "journalId", deserialize = "journalId"))]
+    #[schema(rename = "journalId")]
+    pub journal_id: Option<String>,
 }
 
-impl MediaMoveError {
-    fn new(media_path: String, error: eyre::Report) -> Self {
-        MediaMoveError {
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MediaCleanupReq {
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MediaCleanupResult {
+    pub cleaned: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+impl MediaMoveResult {
+    fn new(media_path: String, journal_id: Option<String>, res: eyre::Result<()>) -> Self {
+        MediaMoveResult {
             media_path,
-            error: error.to_string(),
+            success: res.is_ok(),
+            error: res.err().map(|e| e.to_string()),
+            journal_id,
         }
     }
 }
 
 pub fn router() -> Router {
-    Router::new().route( "/api/v1/media-moves", post(move_media)
-        .with_state(Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap())))
+    Router::new()
+        .route("/api/v1/media-moves", post(move_media))
+        .route("/api/v1/media-moves/preview", post(preview_media_move))
+        .route("/api/v1/media-moves/cleanup", post(cleanup_media_sources))
+        .route("/api/v1/media-moves/:id/undo", post(undo_media_move))
+        .route("/api/v1/media-move-jobs", post(submit_move_job).get(list_move_jobs))
+        .route("/api/v1/media-move-jobs/:id", get(get_move_job))
+        .with_state(Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap()))
 }
 
 #[utoipa::path(post, path = "/api/v1/media-moves",
-    request_body = MediaMoveReq,
+    request_body = [MediaMoveReq],
     responses(
-        (status = 200, description = "Move given media", body = [MediaMoveError])
+        (status = 200, description = "Move given batch of media, returning a result for each one", body = [MediaMoveResult]),
+        (status = 422, description = "Paths of some media are outside the configured roots")
     )
 )]
 pub async fn move_media( State(episode_regex): State<Arc<Regex>>,
-        ctx: Extension<ApiContext>, Json(req): Json<MediaMoveReq>, ) -> Json<Vec<MediaMoveError>> {
-    info!("move_media request received with paylod: {:?}", req);
+        ctx: Extension<ApiContext>, Json(reqs): Json<Vec<MediaMoveReq>>, ) -> http::Result<Json<Vec<MediaMoveResult>>> {
+    info!("move_media request received with paylod: {:?}", reqs);
+
+    let errors: Vec<(String, String)> = reqs.iter()
+        .enumerate()
+        .flat_map(|(i, req)| validation::validate_move_req(&ctx.settings, req)
+            .into_iter()
+            .map(move |(field, e)| (format!("[{}].{}", i, field), e)))
+        .collect();
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    let concurrency = max(1, ctx.settings.mv.batch_concurrency as usize);
+    let results = stream::iter(reqs)
+        .map(|req| move_single_media(ctx.settings.clone(), ctx.db_client.clone(), req, episode_regex.clone()))
+        .buffered(concurrency)
+        .collect()
+        .await;
+
+    Ok(Json(results))
+}
+
+async fn move_single_media(settings: Arc<Settings>, db_client: DbClient, mut req: MediaMoveReq, episode_regex: Arc<Regex>) -> MediaMoveResult {
+    let media_path = req.file_group.path.clone();
+
+    if let Err(e) = resolve_media_type(settings.clone(), &mut req).await {
+        warn!("could not resolve media type for media {:?}: {:?}", req.file_group, e);
+        return MediaMoveResult::new(media_path, None, Err(e));
+    }
+
+    let mut journal = MoveJournalEntry::new(media_path.clone());
+    let move_settings = settings.clone();
+    let res = tokio::task::spawn_blocking(move || {
+        let res = plan_media_move(move_settings.clone(), req, episode_regex)
+            .and_then(|mut plan| {
+                let metadata = plan.metadata.take();
+                plan.execute(&move_settings, &mut journal).map(|_| metadata)
+            });
+        (journal, res)
+    }).await;
+
+    match res {
+        Ok((mut journal, r)) => {
+            if let Ok(Some(metadata)) = &r {
+                metadata::write(&settings, metadata, &mut journal).await;
+            }
+            MediaMoveResult::new(media_path, journal::record(&db_client, &journal).await, r.map(|_| ()))
+        },
+        Err(e) => MediaMoveResult::new(media_path, None, Err(eyre!("move task could not complete: {}", e))),
+    }
+}
+
+#[utoipa::path(post, path = "/api/v1/media-moves/{id}/undo",
+    params(
+        ("id" = String, Path, description = "journal id of the media move to undo"),
+    ),
+    responses(
+        (status = 200, description = "Undo a media move, restoring files where still possible. A partial undo keeps what failed for a retry", body = MoveUndoResult),
+        (status = 404, description = "Media move journal entry not found")
+    )
+)]
+pub async fn undo_media_move(ctx: Extension<ApiContext>, ReqPath(id): ReqPath<String>) -> http::Result<Json<MoveUndoResult>> {
+    info!("undo_media_move request received for id {}", id);
+
+    let mut entry = match ctx.db_client.move_journal_repo().find_entry(&id).await? {
+        Some(entry) => entry,
+        None => return Err(Error::NotFound),
+    };
+
+    if entry.undone {
+        return Err(Error::unprocessable_entity([("id", "media move was already undone")]));
+    }
 
     let settings = ctx.settings.clone();
-    let file_group = req.file_group;
-    let media_path = file_group.path.clone();
-
-    let res = match req.media_type {
-        MediaFileType::MOVIE => move_media_and_subs(MovieMedia::new(settings, file_group)),
-        MediaFileType::TV => move_media_and_subs(TvMedia::new(settings, file_group, episode_regex)),
-        MediaFileType::UNKNOWN => {
-            warn!("unknown media type provided for media {:?}", file_group);
-            return Json(vec![]);
+    let mut undo_entry = entry.clone();
+    let (undo_entry, result) = tokio::task::spawn_blocking(move || {
+        let result = journal::undo(&settings, &mut undo_entry);
+        (undo_entry, result)
+    }).await.wrap_err("undo task could not complete")?;
+
+    if result.partial {
+        warn!("media move {} was only partially undone: {}", &id, result.errors.join(", "));
+        entry = undo_entry;
+    } else {
+        entry.undone = true;
+    }
+    ctx.db_client.move_journal_repo().save_entry(&entry).await?;
+
+    Ok(Json(result))
+}
+
+#[utoipa::path(post, path = "/api/v1/media-moves/cleanup",
+    request_body = MediaCleanupReq,
+    responses(
+        (status = 200, description = "Remove sources of hardlinked media whose torrents were removed", body = MediaCleanupResult),
+        (status = 422, description = "Some paths are outside the downloads root")
+    )
+)]
+pub async fn cleanup_media_sources(ctx: Extension<ApiContext>, Json(req): Json<MediaCleanupReq>) -> http::Result<Json<MediaCleanupResult>> {
+    info!("cleanup_media_sources request received with paylod: {:?}", req);
+
+    let errors: Vec<(&str, String)> = req.paths.iter()
+        .filter(|p| !Path::new(p).is_absolute() || roots::downloads_root_of(&ctx.settings, Path::new(p)).is_none())
+        .map(|p| ("paths", format!("path is outside the downloads roots: {}", p)))
+        .collect();
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    let mut result = MediaCleanupResult { cleaned: vec![], errors: vec![] };
+
+    for path in req.paths {
+        let mut entries: Vec<MoveJournalEntry> = ctx.db_client.move_journal_repo().find_entries_by_media_path(&path).await?
+            .into_iter()
+            .filter(|e| e.import_mode == ImportMode::HARDLINK && !e.undone && !e.cleaned)
+            .collect();
+
+        if entries.is_empty() {
+            result.errors.push(format!("no hardlinked import found for media {}", path));
+            continue;
         }
-    };
 
-    let mut errors = vec![];
-    if let Err(e) = res {
-        errors.push(MediaMoveError::new(media_path, e));
+        let settings = ctx.settings.clone();
+        let sources: Vec<String> = entries.iter().flat_map(|e| e.moves.iter().map(|m| m.src.clone())).collect();
+        let media_path = path.clone();
+        let res = tokio::task::spawn_blocking(move || clean_linked_sources(&settings, &media_path, &sources)).await
+            .wrap_err("cleanup task could not complete")?;
+
+        if let Err(e) = res {
+            result.errors.push(e.to_string());
+            continue;
+        }
+
+        for entry in entries.iter_mut() {
+            entry.cleaned = true;
+            ctx.db_client.move_journal_repo().save_entry(entry).await?;
+        }
+        result.cleaned.push(path);
+    }
+
+    Ok(Json(result))
+}
+
+fn clean_linked_sources(settings: &Settings, media_path: &str, sources: &[String]) -> eyre::Result<()> {
+    if can_clean_media_src(settings, media_path) {
+        if Path::new(media_path).exists() {
+            info!("cleaning linked media src dir {}", media_path);
+            trash::move_to_trash(settings, Path::new(media_path))?;
+        }
+        return Ok(());
+    }
+
+    for src in sources.iter().map(Path::new).filter(|s| s.exists()) {
+        info!("cleaning linked media src file {}", src.to_string_lossy());
+        trash::move_to_trash(settings, src)?;
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(post, path = "/api/v1/media-moves/preview",
+    request_body = MediaMoveReq,
+    responses(
+        (status = 200, description = "Preview the move of given media without changing anything on disk", body = MediaMovePlan),
+        (status = 422, description = "Media type could not be classified or paths are outside the configured roots")
+    )
+)]
+pub async fn preview_media_move( State(episode_regex): State<Arc<Regex>>,
+        ctx: Extension<ApiContext>, Json(mut req): Json<MediaMoveReq>, ) -> http::Result<Json<MediaMovePlan>> {
+    info!("preview_media_move request received with paylod: {:?}", req);
+
+    let errors = validation::validate_move_req(&ctx.settings, &req);
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    if let Err(e) = resolve_media_type(ctx.settings.clone(), &mut req).await {
+        return Err(Error::unprocessable_entity([("type", e.to_string())]));
+    }
+
+    Ok(Json(plan_media_move_task(ctx.settings.clone(), req, episode_regex).await?))
+}
+
+#[utoipa::path(post, path = "/api/v1/media-move-jobs",
+    request_body = MediaMoveReq,
+    responses(
+        (status = 200, description = "Submit a background job moving given media", body = MoveJob),
+        (status = 422, description = "Media type could not be classified, conflicts, not enough free space or paths outside the configured roots")
+    )
+)]
+pub async fn submit_move_job( State(episode_regex): State<Arc<Regex>>,
+        ctx: Extension<ApiContext>, Json(mut req): Json<MediaMoveReq>, ) -> http::Result<Json<MoveJob>> {
+    info!("submit_move_job request received with paylod: {:?}", req);
+
+    let errors = validation::validate_move_req(&ctx.settings, &req);
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    if let Err(e) = resolve_media_type(ctx.settings.clone(), &mut req).await {
+        return Err(Error::unprocessable_entity([("type", e.to_string())]));
+    }
+
+    let media_path = req.file_group.path.clone();
+    let plan = plan_media_move_task(ctx.settings.clone(), req, episode_regex).await?;
+    if plan.has_conflicts() {
+        return Err(Error::unprocessable_entity(plan.conflicts.into_iter().map(|c| ("conflicts", c))));
+    }
+
+    let job = job::submit_job(ctx.settings.clone(), ctx.db_client.clone(), plan, media_path).await?;
+
+    Ok(Json(job))
+}
+
+#[utoipa::path(get, path = "/api/v1/media-move-jobs",
+    params(
+        ("page" = Option<u64>, Query, description = "page to retrieve, starting from 1"),
+        ("size" = Option<u64>, Query, description = "jobs per page"),
+    ),
+    responses(
+        (status = 200, description = "List background move jobs, newest first", body = MoveJobPage)
+    )
+)]
+pub async fn list_move_jobs(ctx: Extension<ApiContext>, Query(params): Query<MoveJobParams>) -> http::Result<Json<MoveJobPage>> {
+    info!("list_move_jobs request received with params {:?}", params);
+
+    Ok(Json(job::page_jobs(&ctx.settings, &ctx.db_client, &params).await?))
+}
+
+#[utoipa::path(get, path = "/api/v1/media-move-jobs/{id}",
+    params(
+        ("id" = String, Path, description = "id of the move job to retrieve"),
+    ),
+    responses(
+        (status = 200, description = "Get status of a background move job", body = MoveJob),
+        (status = 404, description = "Move job not found")
+    )
+)]
+pub async fn get_move_job(ctx: Extension<ApiContext>, ReqPath(id): ReqPath<String>) -> http::Result<Json<MoveJob>> {
+    info!("get_move_job request received for id {}", id);
+
+    match ctx.db_client.move_job_repo().find_job(&id).await? {
+        Some(job) => Ok(Json(job)),
+        None => Err(Error::NotFound),
+    }
+}
+
+async fn resolve_media_type(settings: Arc<Settings>, req: &mut MediaMoveReq) -> eyre::Result<()> {
+    if req.media_type != MediaFileType::UNKNOWN {
+        return Ok(());
+    }
+
+    let classification = classify::classify_media(settings.clone(), req.file_group.clone()).await?;
+    if classification.confidence < settings.classify.move_min_confidence {
+        return Err(eyre!("media type could not be classified confidently, best guess is {:?} at {}%: {}",
+            classification.media_type, classification.confidence, classification.reasons.join(", ")));
+    }
+
+    info!("using classified media type {:?} for media {}", classification.media_type, &req.file_group.path);
+    req.media_type = classification.media_type;
+    Ok(())
+}
+
+// planning walks and sniffs the release on disk, so it's kept off the async workers
+async fn plan_media_move_task(settings: Arc<Settings>, req: MediaMoveReq, episode_regex: Arc<Regex>) -> eyre::Result<MediaMovePlan> {
+    tokio::task::spawn_blocking(move || plan_media_move(settings, req, episode_regex)).await
+        .wrap_err("plan media move task could not complete")?
+}
+
+fn plan_media_move(settings: Arc<Settings>, req: MediaMoveReq, episode_regex: Arc<Regex>) -> eyre::Result<MediaMovePlan> {
+    let media_type = req.media_type;
+    let on_conflict = req.on_conflict;
+    let import_mode = req.import_mode.unwrap_or(settings.mv.import_mode);
+    let genres = req.genres.clone();
+    let (file_group, description) = req.into_parts();
+
+    if media_type == MediaFileType::UNKNOWN {
+        return Err(eyre!("unknown media type provided for media {:?}", file_group));
+    }
+    let dest_root = roots::choose_library_root(&settings, media_type, &file_group, &genres)?;
+
+    let mut plan = match media_type {
+        MediaFileType::MOVIE => create_plan(&MovieMedia::new(settings.clone(), file_group, dest_root), on_conflict, import_mode, description)?,
+        _ => create_plan(&TvMedia::new(settings.clone(), file_group, dest_root, episode_regex), on_conflict, import_mode, description)?,
     };
 
-    Json(errors)
+    if let Err(e) = plan.check_free_space(&settings) {
+        plan.conflicts.push(e.to_string());
+    }
+    Ok(plan)
 }
 
 trait Media {
+    fn media_type(&self) -> MediaFileType;
     fn already_exists(&self) -> bool;
-    fn dest_root(&self) -> &str;
+    fn dest_root(&self) -> &Path;
     fn file_group(&self) -> &MediaFileGroup;
     fn settings(&self) -> &Settings;
-    fn move_subs(&self, dest: &Path, subs: Vec<DirEntry>) -> eyre::Result<()>;
+    fn sub_video<'a>(&self, namer: &SubsNamer, sub: &Path, videos: &'a [PlannedMove]) -> Option<&'a PlannedMove>;
+    fn sub_fallback_dest(&self, dest: &Path, sub: &Path) -> PathBuf;
 }
 
 struct MovieMedia {
     settings: Arc<Settings>,
     file_group: MediaFileGroup,
+    dest_root: PathBuf,
 }
 
 impl MovieMedia {
-    fn new(settings: Arc<Settings>, file_group: MediaFileGroup) -> Self {
+    fn new(settings: Arc<Settings>, file_group: MediaFileGroup, dest_root: PathBuf) -> Self {
         MovieMedia {
             settings,
             file_group,
+            dest_root,
         }
     }
 }
 
 impl Media for MovieMedia {
+    fn media_type(&self) -> MediaFileType {
+        MediaFileType::MOVIE
+    }
+
     fn already_exists(&self) -> bool {
-        let movie_path =
-            Path::new(&self.settings.filesystem.movies_path).join(&self.file_group.name);
+        let movie_path = self.dest_root.join(&self.file_group.name);
         movie_path.exists() && movie_path.is_dir()
     }
 
-    fn dest_root(&self) -> &str {
-        &self.settings.filesystem.movies_path
+    fn dest_root(&self) -> &Path {
+        &self.dest_root
     }
 
     fn file_group(&self) -> &MediaFileGroup {
@@ -120,40 +464,44 @@ impl Media for MovieMedia {
         &self.settings
     }
 
-    fn move_subs(&self, dest: &Path, subs: Vec<DirEntry>) -> eyre::Result<()> {
-        subs.iter()
-            .map(|sub| {
-                let src = sub.path();
-                let dest = dest.join(src.file_name().unwrap());
-                files::move_files(src, &dest)
-            })
-            .collect()
+    fn sub_video<'a>(&self, _namer: &SubsNamer, _sub: &Path, videos: &'a [PlannedMove]) -> Option<&'a PlannedMove> {
+        videos.iter().max_by_key(|v| fs::metadata(&v.src).map(|d| d.len()).unwrap_or_default())
+    }
+
+    fn sub_fallback_dest(&self, dest: &Path, sub: &Path) -> PathBuf {
+        dest.join(sub.file_name().unwrap())
     }
 }
 
 struct TvMedia {
     settings: Arc<Settings>,
     file_group: MediaFileGroup,
+    dest_root: PathBuf,
     episode_regex: Arc<Regex>,
 }
 
 impl TvMedia {
-    fn new(settings: Arc<Settings>, file_group: MediaFileGroup, episode_regex: Arc<Regex>) -> Self {
+    fn new(settings: Arc<Settings>, file_group: MediaFileGroup, dest_root: PathBuf, episode_regex: Arc<Regex>) -> Self {
         TvMedia {
             settings,
             file_group,
+            dest_root,
             episode_regex,
         }
     }
 }
 
 impl Media for TvMedia {
+    fn media_type(&self) -> MediaFileType {
+        MediaFileType::TV
+    }
+
     fn already_exists(&self) -> bool {
         false
     }
 
-    fn dest_root(&self) -> &str {
-        &self.settings.filesystem.tv_path
+    fn dest_root(&self) -> &Path {
+        &self.dest_root
     }
 
     fn file_group(&self) -> &MediaFileGroup {
@@ -164,105 +512,158 @@ impl Media for TvMedia {
         &self.settings
     }
 
-    fn move_subs(&self, dest: &Path, subs: Vec<DirEntry>) -> eyre::Result<()> {
-        subs.iter()
-            .map(|sub| {
-                let src = sub.path();
+    fn sub_video<'a>(&self, namer: &SubsNamer, sub: &Path, videos: &'a [PlannedMove]) -> Option<&'a PlannedMove> {
+        namer.match_episode(sub, videos)
+    }
 
-                let mut sub_name = src.file_name().unwrap().to_string_lossy().into_owned();
-                for segment in src.iter() {
-                    let segment = segment.to_string_lossy().into_owned();
-                    if self.episode_regex.is_match(&segment) {
-                        sub_name = format!("{}.{}", segment, sub_name);
-                        break;
-                    }
-                }
+    fn sub_fallback_dest(&self, dest: &Path, sub: &Path) -> PathBuf {
+        let mut sub_name = sub.file_name().unwrap().to_string_lossy().into_owned();
+        for segment in sub.iter() {
+            let segment = segment.to_string_lossy().into_owned();
+            if self.episode_regex.is_match(&segment) {
+                sub_name = format!("{}.{}", segment, sub_name);
+                break;
+            }
+        }
 
-                let new_dest = dest.join(SUBS_DIR).join(&sub_name);
-                files::move_files(src, &new_dest)
-            })
-            .collect()
+        dest.join(SUBS_DIR).join(&sub_name)
     }
 }
 
-fn move_media_and_subs<M: Media>(media: M) -> eyre::Result<()> {
+fn create_plan<M: Media>(media: &M, on_conflict: ConflictStrategy, import_mode: ImportMode, 
+        description: Option<MediaDescription>) -> eyre::Result<MediaMovePlan> {
+    let mut plan = MediaMovePlan { import_mode, ..Default::default() };
+    let file_group = media.file_group();
+    let mut media_dest = media.dest_root().join(&file_group.name);
+
     if media.already_exists() {
-        let msg = format!( "media with path already exists: {}", &media.file_group().path);
-        warn!(msg);
-        return Err(eyre!(msg));
+        let existing = media_dest.to_string_lossy().into_owned();
+        let videos: Vec<PathBuf> = file_group.videos.iter()
+            .map(|v| Path::new(&file_group.path).join(v))
+            .collect();
+        let quality_parser = QualityParser::new();
+
+        match on_conflict {
+            ConflictStrategy::FAIL => {
+                plan.conflicts.push(format!("media with path already exists: {}", &file_group.path));
+            },
+            ConflictStrategy::SKIP => {
+                plan.skipped.push(format!("media already exists at {}", existing));
+                return Ok(plan);
+            },
+            ConflictStrategy::REPLACE_IF_BETTER => {
+                if !conflict::is_upgrade(&quality_parser, &videos, &media_dest, media.settings().mv.subs_max_depth)? {
+                    plan.skipped.push(format!("existing media at {} has same or better quality", existing));
+                    return Ok(plan);
+                }
+                plan.replaced.push(existing);
+            },
+            ConflictStrategy::KEEP_BOTH => {
+                media_dest = conflict::keep_both_dest(&quality_parser, &media_dest, &videos);
+            },
+            ConflictStrategy::MERGE => {
+                info!("merging media into existing path {}", existing);
+            },
+        }
     }
 
-    let dest_root = media.dest_root();
+    let classifier = ReleaseClassifier::new(media.settings())?;
+    let release_src = Path::new(&file_group.path).to_path_buf();
+    let mut selected_videos = HashSet::new();
+
+    for video in &file_group.videos {
+        let video_src = release_src.join(video);
+        selected_videos.insert(video_src.clone());
 
-    for video in &media.file_group().videos {
-        let media_src = Path::new(&media.file_group().path).join(video);
-        let media_dest = Path::new(dest_root)
-            .join(&media.file_group().name)
-            .join(video);
-        files::move_files(&media_src, &media_dest)?;
+        match classifier.classify(media.settings(), &release_src, &video_src, true) {
+            ReleaseFileKind::VIDEO => {
+                let video_dest = media_dest.join(video);
+                if plan.accepts_dest(&video_dest, on_conflict) {
+                    plan.videos.push(PlannedMove::new(&video_src, &video_dest));
+                }
+            },
+            kind => plan_release_file(media.settings(), &mut plan, kind, &video_src, &media_dest, on_conflict),
+        }
     }
 
-    let subs_src = Path::new(&media.file_group().path).to_path_buf();
-    let subs_src_str = subs_src.to_string_lossy();
+    if media.settings().mv.write_nfo || media.settings().mv.download_artwork {
+        plan.metadata = description.map(|d| MediaMetadata::new(media.media_type(), &media_dest, d));
+    }
 
-    if &subs_src_str == &media.settings().filesystem.downloads_path {
+    if roots::is_downloads_root(media.settings(), &release_src) {
         info!("path to move subs is root Downloads path, skipping operation");
-        return Ok(());
+        plan.collect_dirs_to_create();
+        return Ok(plan);
     }
 
-    let mut subs = files::walk_files(&subs_src, media.settings().mv.subs_max_depth)?;
-    subs = subs
-        .into_iter()
-        .filter(|sub| exclude_non_subs(media.settings(), sub))
-        .collect();
+    let mut subs: Vec<DirEntry> = vec![];
+    for file in files::walk_files(&release_src, media.settings().mv.subs_max_depth)? {
+        if selected_videos.contains(file.path()) {
+            continue;
+        }
 
-    match subs.is_empty() {
-        true => info!("no subs found in subs src {}", &subs_src_str),
-        false => {
-            let subs_dest = Path::new(dest_root).join(&media.file_group().name);
-            media.move_subs(&subs_dest, subs)?;
-        },
-    };
+        match classifier.classify(media.settings(), &release_src, file.path(), false) {
+            ReleaseFileKind::SUBTITLE => subs.push(file),
+            ReleaseFileKind::VIDEO => info!("video {} was not selected for move, ignoring it", file.path().to_string_lossy()),
+            kind => plan_release_file(media.settings(), &mut plan, kind, file.path(), &media_dest, on_conflict),
+        }
+    }
 
-    clean_media_src(media.settings(), &media.file_group().path)?;
+    if subs.is_empty() {
+        info!("no subs found in subs src {}", release_src.to_string_lossy());
+    }
 
-    Ok(())
+    let namer = SubsNamer::new();
+    for sub in subs {
+        let ext = subs_import::target_ext(media.settings(), sub.path());
+        let sub_dest = match media.sub_video(&namer, sub.path(), &plan.videos) {
+            Some(video) => namer.dest(sub.path(), &ext, Path::new(&video.dest), &plan.subs),
+            None => media.sub_fallback_dest(&media_dest, sub.path()).with_extension(&ext),
+        };
+        if plan.accepts_dest(&sub_dest, on_conflict) {
+            plan.subs.push(PlannedMove::new(sub.path(), &sub_dest));
+        }
+    }
+
+    if import_mode == ImportMode::HARDLINK {
+        info!("media src {} kept for seeding, it will be cleaned once its torrent is removed", &file_group.path);
+    } else if can_clean_media_src(media.settings(), &file_group.path) {
+        plan.dirs_to_delete.push(file_group.path.clone());
+    }
+
+    plan.collect_dirs_to_create();
+    Ok(plan)
 }
 
-fn exclude_non_subs(settings: &Settings, sub: &DirEntry) -> bool {
-    settings
-        .mv
-        .subs_ext
-        .iter()
-        .any(|ext| match sub.path().extension() {
-            Some(sub_ext) => OsStr::new(ext) == sub_ext,
-            None => false,
-        })
+fn plan_release_file(settings: &Settings, plan: &mut MediaMovePlan, kind: ReleaseFileKind, 
+        src: &Path, media_dest: &Path, on_conflict: ConflictStrategy) {
+    match release::action_for(settings, kind) {
+        Some(ReleaseFileAction::EXTRAS) => {
+            let dest = media_dest.join(EXTRAS_DIR).join(src.file_name().unwrap_or_default());
+            if plan.accepts_dest(&dest, on_conflict) {
+                plan.extras.push(PlannedMove::new(src, &dest));
+            }
+        },
+        Some(ReleaseFileAction::DELETE) if plan.import_mode == ImportMode::HARDLINK => {
+            info!("{:?} file {} kept for seeding", kind, src.to_string_lossy());
+        },
+        Some(ReleaseFileAction::DELETE) => plan.deleted.push(src.to_string_lossy().into_owned()),
+        Some(ReleaseFileAction::IGNORE) | None => info!("ignoring {:?} file {}", kind, src.to_string_lossy()),
+    }
 }
 
-fn clean_media_src(settings: &Settings, path_str: &str) -> eyre::Result<()> {
-    if &settings.filesystem.downloads_path == path_str
-        || &settings.filesystem.movies_path == path_str
-        || &settings.filesystem.tv_path == path_str
-    {
+fn can_clean_media_src(settings: &Settings, path_str: &str) -> bool {
+    let path = files::canonicalize(Path::new(path_str));
+
+    if roots::is_root(settings, &path) {
         info!( "cleaning aborted, media src dir is important folder: {}", path_str);
-        return Ok(());
+        return false;
     }
 
-    let path = Path::new(path_str);
-    warn!("{:#?}", path);
-
-    for restricted_path in &settings.mv.restricted_remove_paths {
-        match path.iter().last() {
-            Some(last_segment) => {
-                if OsStr::new(restricted_path) == last_segment {
-                    info!( "clean media src dir aborted, restricted folder: {}", restricted_path);
-                    return Ok(());
-                }
-            }
-            None => (),
-        }
+    if let Some(restricted_path) = PathMatcher::new(&settings.mv.restricted_remove_paths, false).find_match(&path) {
+        info!( "clean media src dir aborted, restricted folder: {}", restricted_path);
+        return false;
     }
 
-    files::delete_dir(path)
+    true
 }
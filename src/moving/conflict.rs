@@ -0,0 +1,75 @@
+use std::{cmp::Ordering, fs, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{files, quality::QualityParser};
+
+const KEEP_BOTH_DEFAULT_LABEL: &str = "Copy";
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub enum ConflictStrategy {
+    #[default]
+    FAIL,
+    SKIP,
+    REPLACE_IF_BETTER,
+    KEEP_BOTH,
+    MERGE,
+}
+
+pub fn is_upgrade(parser: &QualityParser, new_videos: &[PathBuf], existing_dir: &Path, max_depth: u8) -> eyre::Result<bool> {
+    let existing_files = files::walk_files(existing_dir, max_depth)?
+        .into_iter()
+        .map(|f| f.into_path())
+        .collect::<Vec<PathBuf>>();
+
+    let (new, existing) = match (largest_file(new_videos), largest_file(&existing_files)) {
+        (None, _) => return Ok(false),
+        (Some(_), None) => return Ok(true),
+        (Some(new), Some(existing)) => (new, existing),
+    };
+
+    let new_quality = parser.parse_file(&quality_name(&new.0));
+    let existing_quality = parser.parse_file(&quality_name(&existing.0));
+
+    Ok(match new_quality.compare(&existing_quality) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => new.1 > existing.1,
+    })
+}
+
+pub fn keep_both_dest(parser: &QualityParser, dest: &Path, new_videos: &[PathBuf]) -> PathBuf {
+    let label = largest_file(new_videos)
+        .map(|(p, _)| parser.parse_file(&quality_name(&p)).label())
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| KEEP_BOTH_DEFAULT_LABEL.to_owned());
+    let name = dest.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+    let mut candidate = dest.with_file_name(format!("{} {{edition-{}}}", name, label));
+    let mut index = 2;
+    while candidate.exists() {
+        candidate = dest.with_file_name(format!("{} {{edition-{} {}}}", name, label, index));
+        index += 1;
+    }
+
+    candidate
+}
+
+fn largest_file(paths: &[PathBuf]) -> Option<(PathBuf, u64)> {
+    paths.iter()
+        .map(|p| (p.clone(), fs::metadata(p).map(|d| d.len()).unwrap_or_default()))
+        .max_by_key(|(_, size)| *size)
+}
+
+// the release folder name often holds the quality the video name lacks
+fn quality_name(path: &Path) -> PathBuf {
+    path.iter()
+        .rev()
+        .take(2)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect()
+}
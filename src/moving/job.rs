@@ -0,0 +1,217 @@
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{config::Settings, db::DbClient};
+
+use super::{plan::{MediaMovePlan, MoveProgress}, journal::{self, MoveJournalEntry}, metadata};
+
+const PROGRESS_PERSIST_INTERVAL: Duration = Duration::from_secs(1);
+const INTERRUPTED_ERROR: &str = "job interrupted by server restart";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum MoveJobStatus {
+    QUEUED,
+    RUNNING,
+    COMPLETED,
+    FAILED,
+}
+
+impl FromStr for MoveJobStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "QUEUED" => Ok(MoveJobStatus::QUEUED),
+            "RUNNING" => Ok(MoveJobStatus::RUNNING),
+            "COMPLETED" => Ok(MoveJobStatus::COMPLETED),
+            "FAILED" => Ok(MoveJobStatus::FAILED),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MoveJobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveJobStatus::QUEUED => write!(f, "QUEUED"),
+            MoveJobStatus::RUNNING => write!(f, "RUNNING"),
+            MoveJobStatus::COMPLETED => write!(f, "COMPLETED"),
+            MoveJobStatus::FAILED => write!(f, "FAILED"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MoveJob {
+    pub id: String,
+    #[serde(rename(serialize = "mediaPath", deserialize = "mediaPath"))]
+    #[schema(rename = "mediaPath")]
+    pub media_path: String,
+    pub status: MoveJobStatus,
+    #[serde(rename(serialize = "bytesDone", deserialize = "bytesDone"))]
+    #[schema(rename = "bytesDone")]
+    pub bytes_done: u64,
+    #[serde(rename(serialize = "bytesTotal", deserialize = "bytesTotal"))]
+    #[schema(rename = "bytesTotal")]
+    pub bytes_total: u64,
+    #[serde(rename(serialize = "currentFile", deserialize = "currentFile"))]
+    #[schema(rename = "currentFile")]
+    pub current_file: Option<String>,
+    pub errors: Vec<String>,
+    #[serde(rename(serialize = "journalId", deserialize = "journalId"))]
+    #[schema(rename = "journalId")]
+    pub journal_id: Option<String>,
+    pub created: i64,
+    pub updated: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MoveJobPage {
+    pub items: Vec<MoveJob>,
+    pub total: u64,
+    pub page: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MoveJobParams {
+    pub page: Option<u64>,
+    pub size: Option<u64>,
+}
+
+impl MoveJob {
+    pub fn new(media_path: String, bytes_total: u64) -> Self {
+        let now = Utc::now().timestamp_millis();
+        MoveJob {
+            id: format!("{:032x}", rand::random::<u128>()),
+            media_path,
+            status: MoveJobStatus::QUEUED,
+            bytes_done: 0,
+            bytes_total,
+            current_file: None,
+            errors: vec![],
+            journal_id: None,
+            created: now,
+            updated: now,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, MoveJobStatus::COMPLETED | MoveJobStatus::FAILED)
+    }
+
+    fn update_progress(&mut self, progress: MoveProgress) {
+        self.bytes_done = progress.bytes_done;
+        self.current_file = progress.current_file;
+    }
+}
+
+#[async_trait]
+pub trait MoveJobRepo: Send + Sync {
+    async fn save_job(&self, job: &MoveJob) -> eyre::Result<()>;
+    async fn find_job(&self, id: &str) -> eyre::Result<Option<MoveJob>>;
+    async fn find_jobs(&self, skip: u64, limit: u64) -> eyre::Result<Vec<MoveJob>>;
+    async fn count_jobs(&self) -> eyre::Result<u64>;
+    async fn find_unfinished_jobs(&self) -> eyre::Result<Vec<MoveJob>>;
+}
+
+// newest jobs come first
+pub async fn page_jobs(settings: &Settings, db_client: &DbClient, params: &MoveJobParams) -> eyre::Result<MoveJobPage> {
+    let page = params.page.unwrap_or(1).max(1);
+    let size = params.size.unwrap_or(settings.mv.job_page_size).clamp(1, settings.mv.max_job_page_size);
+    let skip = (page - 1).saturating_mul(size);
+
+    let repo = db_client.move_job_repo();
+    let items = repo.find_jobs(skip, size).await?;
+    let total = repo.count_jobs().await?;
+
+    Ok(MoveJobPage { items, total, page, size })
+}
+
+pub async fn submit_job(settings: Arc<Settings>, db_client: DbClient, plan: MediaMovePlan, media_path: String) -> eyre::Result<MoveJob> {
+    let job = MoveJob::new(media_path, plan.total_bytes());
+    db_client.move_job_repo().save_job(&job).await?;
+
+    info!("submitted move job {} for media {}", &job.id, &job.media_path);
+    tokio::spawn(run_job(settings, db_client, plan, job.clone()));
+
+    Ok(job)
+}
+
+async fn run_job(settings: Arc<Settings>, db_client: DbClient, mut plan: MediaMovePlan, mut job: MoveJob) {
+    job.status = MoveJobStatus::RUNNING;
+    persist(&db_client, &mut job).await;
+
+    let (progress_tx, progress_rx) = watch::channel(MoveProgress::default());
+    let mut journal = MoveJournalEntry::new(job.media_path.clone());
+    let media_metadata = plan.metadata.take();
+    let move_settings = settings.clone();
+    let mut handle = tokio::task::spawn_blocking(move || {
+        let res = plan.execute_with_progress(&move_settings, &mut journal, &mut |p| { let _ = progress_tx.send(p); });
+        (journal, res)
+    });
+
+    let mut interval = tokio::time::interval(PROGRESS_PERSIST_INTERVAL);
+    let res = loop {
+        tokio::select! {
+            res = &mut handle => break res,
+            _ = interval.tick() => {
+                let progress = progress_rx.borrow().clone();
+                job.update_progress(progress);
+                persist(&db_client, &mut job).await;
+            },
+        }
+    };
+
+    let progress = progress_rx.borrow().clone();
+    job.update_progress(progress);
+    job.current_file = None;
+
+    match res {
+        Ok((mut journal, res)) => {
+            if let (Ok(_), Some(media_metadata)) = (&res, &media_metadata) {
+                metadata::write(&settings, media_metadata, &mut journal).await;
+            }
+            job.journal_id = journal::record(&db_client, &journal).await;
+            match res {
+                Ok(_) => job.status = MoveJobStatus::COMPLETED,
+                Err(e) => {
+                    job.status = MoveJobStatus::FAILED;
+                    job.errors.push(e.to_string());
+                },
+            }
+        },
+        Err(e) => {
+            job.status = MoveJobStatus::FAILED;
+            job.errors.push(format!("move job could not complete: {}", e));
+        },
+    }
+
+    info!("move job {} finished with status {:?}", &job.id, job.status);
+    persist(&db_client, &mut job).await;
+}
+
+async fn persist(db_client: &DbClient, job: &mut MoveJob) {
+    job.updated = Utc::now().timestamp_millis();
+    if let Err(e) = db_client.move_job_repo().save_job(job).await {
+        warn!("could not persist move job {}: {:?}", &job.id, e);
+    }
+}
+
+pub async fn fail_interrupted_jobs(db_client: &DbClient) -> eyre::Result<()> {
+    for mut job in db_client.move_job_repo().find_unfinished_jobs().await? {
+        warn!("marking interrupted move job {} as failed", &job.id);
+        job.status = MoveJobStatus::FAILED;
+        job.current_file = None;
+        job.errors.push(INTERRUPTED_ERROR.to_owned());
+        persist(db_client, &mut job).await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{config::Settings, db::DbClient, files, rename::MediaFileType, roots, trash::{self, TrashItem}};
+
+use super::{plan::PlannedMove, ImportMode};
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MoveJournalEntry {
+    pub id: String,
+    #[serde(rename(serialize = "mediaPath", deserialize = "mediaPath"))]
+    #[schema(rename = "mediaPath")]
+    pub media_path: String,
+    pub moves: Vec<PlannedMove>,
+    #[serde(default)]
+    pub trashed: Vec<TrashItem>,
+    #[serde(default)]
+    pub replaced: Vec<TrashItem>,
+    #[serde(default)]
+    pub rewritten: Vec<TrashItem>,
+    #[serde(default, rename(serialize = "createdFiles", deserialize = "createdFiles"))]
+    #[schema(rename = "createdFiles")]
+    pub created_files: Vec<String>,
+    #[serde(rename(serialize = "importMode", deserialize = "importMode"))]
+    #[schema(rename = "importMode")]
+    pub import_mode: ImportMode,
+    pub undone: bool,
+    pub cleaned: bool,
+    pub created: i64,
+}
+
+impl MoveJournalEntry {
+    pub fn new(media_path: String) -> Self {
+        MoveJournalEntry {
+            id: format!("{:032x}", rand::random::<u128>()),
+            media_path,
+            moves: vec![],
+            trashed: vec![],
+            replaced: vec![],
+            rewritten: vec![],
+            created_files: vec![],
+            import_mode: ImportMode::MOVE,
+            undone: false,
+            cleaned: false,
+            created: Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn record_move(&mut self, planned: &PlannedMove) {
+        self.moves.push(planned.clone());
+    }
+
+    pub fn record_trashed(&mut self, item: TrashItem) {
+        self.trashed.push(item);
+    }
+
+    pub fn record_replaced(&mut self, item: TrashItem) {
+        self.replaced.push(item);
+    }
+
+    // the original content of a rewritten file, trashed under the path it was moved from
+    pub fn record_rewritten(&mut self, item: TrashItem) {
+        self.rewritten.push(item);
+    }
+
+    pub fn record_renamed(&mut self, dest: &str, renamed: &Path) {
+        if let Some(planned) = self.moves.iter_mut().find(|m| m.dest == dest) {
+            planned.dest = renamed.to_string_lossy().into_owned();
+        }
+    }
+
+    pub fn record_created_file(&mut self, path: &Path) {
+        self.created_files.push(path.to_string_lossy().into_owned());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty() && self.trashed.is_empty() && self.replaced.is_empty() && self.created_files.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MoveUndoResult {
+    pub restored: Vec<PlannedMove>,
+    pub errors: Vec<String>,
+    pub partial: bool,
+}
+
+#[async_trait]
+pub trait MoveJournalRepo: Send + Sync {
+    async fn save_entry(&self, entry: &MoveJournalEntry) -> eyre::Result<()>;
+    async fn find_entry(&self, id: &str) -> eyre::Result<Option<MoveJournalEntry>>;
+    async fn find_entries_by_media_path(&self, media_path: &str) -> eyre::Result<Vec<MoveJournalEntry>>;
+}
+
+pub async fn record(db_client: &DbClient, entry: &MoveJournalEntry) -> Option<String> {
+    if entry.is_empty() {
+        return None;
+    }
+
+    match db_client.move_journal_repo().save_entry(entry).await {
+        Ok(_) => Some(entry.id.clone()),
+        Err(e) => {
+            warn!("could not record move journal entry for media {}: {:?}", &entry.media_path, e);
+            None
+        },
+    }
+}
+
+// whatever could not be undone stays in the entry, so the undo can be retried
+pub fn undo(settings: &Settings, entry: &mut MoveJournalEntry) -> MoveUndoResult {
+    let mut result = MoveUndoResult { restored: vec![], errors: vec![], partial: false };
+
+    // trashed src dirs come back with their contents first, so moved files have a place to return to
+    entry.trashed = restore_all_trashed(settings, &entry.trashed, &mut result.errors);
+
+    let library_paths: Vec<PathBuf> = roots::library_roots(settings, MediaFileType::UNKNOWN).into_iter()
+        .map(|r| PathBuf::from(r.path))
+        .collect();
+    let library_roots: Vec<&Path> = library_paths.iter().map(|p| p.as_path()).collect();
+
+    // generated files go first so they don't keep the media folder from being cleaned up
+    entry.created_files.retain(|created| {
+        let created = Path::new(created);
+        if !created.is_file() {
+            return false;
+        }
+        match fs::remove_file(created) {
+            Ok(_) => {
+                info!("removed generated file {}", created.to_string_lossy());
+                false
+            },
+            Err(e) => {
+                result.errors.push(format!("could not remove generated file {}: {}", created.to_string_lossy(), e));
+                true
+            },
+        }
+    });
+
+    let mut failed_moves = vec![];
+    for planned in entry.moves.iter().rev() {
+        let rewritten = entry.rewritten.iter().find(|i| i.original_path == planned.src);
+        match undo_move(settings, entry.import_mode, planned, rewritten, &library_roots) {
+            Ok(restored) => result.restored.push(restored),
+            Err(e) => {
+                result.errors.push(e.to_string());
+                failed_moves.push(planned.clone());
+            },
+        }
+    }
+    failed_moves.reverse();
+    entry.rewritten.retain(|i| failed_moves.iter().any(|m| m.src == i.original_path));
+    entry.moves = failed_moves;
+
+    // replaced library media goes back last, once the new media has left its place
+    entry.replaced = restore_all_trashed(settings, &entry.replaced, &mut result.errors);
+
+    result.partial = !result.errors.is_empty();
+    result
+}
+
+fn undo_move(settings: &Settings, import_mode: ImportMode, planned: &PlannedMove, 
+        rewritten: Option<&TrashItem>, library_roots: &[&Path]) -> eyre::Result<PlannedMove> {
+    let moved = Path::new(&planned.dest);
+    let original = Path::new(&planned.src);
+
+    if !moved.is_file() {
+        return Err(eyre!("moved file no longer exists: {}", &planned.dest));
+    }
+
+    // the moved file holds rewritten content, the original comes back from trash instead
+    if let Some(item) = rewritten {
+        if original.exists() {
+            return Err(eyre!("original path is already taken: {}", &planned.src));
+        }
+        restore_trashed(settings, item)?;
+        fs::remove_file(moved).wrap_err_with(|| format!("could not remove rewritten file {}", &planned.dest))?;
+        info!("restored original content of {} to {}", &planned.dest, &planned.src);
+        files::delete_empty_parents(moved, library_roots);
+        return Ok(PlannedMove::new(moved, original));
+    }
+
+    if original.exists() && import_mode == ImportMode::HARDLINK {
+        fs::remove_file(moved).wrap_err_with(|| format!("could not remove linked file {}", &planned.dest))?;
+        info!("removed linked file {}, source {} is still in place", &planned.dest, &planned.src);
+        files::delete_empty_parents(moved, library_roots);
+        return Ok(PlannedMove::new(moved, original));
+    }
+
+    if original.exists() {
+        return Err(eyre!("original path is already taken: {}", &planned.src));
+    }
+
+    files::move_files(moved, original, settings.mv.verify_checksum)?;
+    info!("restored moved file {} to {}", &planned.dest, &planned.src);
+    files::delete_empty_parents(moved, library_roots);
+    Ok(PlannedMove::new(moved, original))
+}
+
+// returns the items that could not be restored, in their journal order
+fn restore_all_trashed(settings: &Settings, items: &[TrashItem], errors: &mut Vec<String>) -> Vec<TrashItem> {
+    let mut failed = vec![];
+    for item in items.iter().rev() {
+        match restore_trashed(settings, item) {
+            Ok(_) => info!("restored trashed path {}", &item.original_path),
+            Err(e) => {
+                errors.push(e.to_string());
+                failed.push(item.clone());
+            },
+        }
+    }
+    failed.reverse();
+    failed
+}
+
+fn restore_trashed(settings: &Settings, item: &TrashItem) -> eyre::Result<()> {
+    if !item.is_in_trash(settings) {
+        return Err(eyre!("trashed path {} is no longer in trash", &item.original_path));
+    }
+    trash::restore(settings, item)
+}
@@ -0,0 +1,171 @@
+use std::{path::Path, sync::OnceLock};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{config::Settings, files, images, rename::{MediaDescription, MediaFileType}};
+
+use super::journal::MoveJournalEntry;
+
+pub const MOVIE_NFO: &str = "movie.nfo";
+pub const TV_NFO: &str = "tvshow.nfo";
+pub const POSTER_FILE: &str = "poster.jpg";
+pub const FANART_FILE: &str = "fanart.jpg";
+
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+static ARTWORK_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MediaMetadata {
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    #[schema(rename = "type")]
+    pub media_type: MediaFileType,
+    pub dir: String,
+    pub description: MediaDescription,
+}
+
+impl MediaMetadata {
+    pub fn new(media_type: MediaFileType, dir: &Path, description: MediaDescription) -> Self {
+        MediaMetadata {
+            media_type,
+            dir: dir.to_string_lossy().into_owned(),
+            description,
+        }
+    }
+}
+
+// failures here don't fail the import, the media is already in place by now
+pub async fn write(settings: &Settings, metadata: &MediaMetadata, journal: &mut MoveJournalEntry) {
+    let dir = Path::new(&metadata.dir);
+    let desc = &metadata.description;
+
+    if settings.mv.write_nfo {
+        let path = dir.join(nfo_file(metadata.media_type));
+        if is_new(&path) {
+            match tokio::fs::write(&path, nfo(settings, metadata.media_type, desc)).await {
+                Ok(_) => journal.record_created_file(&path),
+                Err(e) => warn!("could not write nfo file {}: {:?}", path.to_string_lossy(), e),
+            }
+        }
+    }
+
+    if settings.mv.download_artwork {
+        let client = ARTWORK_CLIENT.get_or_init(reqwest::Client::new);
+        let artwork = [
+            (POSTER_FILE, full_size_poster(settings, &desc.poster_url)),
+            (FANART_FILE, images::original_url(&desc.fanart_url)),
+        ];
+        for (file, url) in artwork {
+            let path = dir.join(file);
+            if url.is_empty() || !is_new(&path) {
+                continue;
+            }
+
+            match download(client, &url, &path, settings.mv.artwork_max_bytes).await {
+                Ok(_) => journal.record_created_file(&path),
+                Err(e) => warn!("could not download artwork {} to {}: {:?}", url, path.to_string_lossy(), e),
+            }
+        }
+    }
+}
+
+pub fn nfo_file(media_type: MediaFileType) -> &'static str {
+    match media_type {
+        MediaFileType::TV => TV_NFO,
+        _ => MOVIE_NFO,
+    }
+}
+
+// search results carry thumbnail sized posters, media servers get the full size one
+fn full_size_poster(settings: &Settings, url: &str) -> String {
+    let url = images::original_url(url);
+    let online = &settings.online;
+    if online.poster_base.is_empty() || online.full_poster_base.is_empty() {
+        return url;
+    }
+
+    match url.strip_prefix(online.poster_base.as_str()) {
+        Some(path) => format!("{}{}", &online.full_poster_base, path),
+        None => url,
+    }
+}
+
+pub fn nfo(settings: &Settings, media_type: MediaFileType, desc: &MediaDescription) -> String {
+    let root = match media_type {
+        MediaFileType::TV => "tvshow",
+        _ => "movie",
+    };
+
+    let mut lines = vec![XML_HEADER.to_owned(), format!("<{}>", root)];
+    lines.push(tag(1, "title", &desc.title));
+    if let Some(year) = desc.date.get(..4).filter(|y| y.chars().all(|c| c.is_ascii_digit())) {
+        lines.push(tag(1, "year", year));
+    }
+    if NaiveDate::parse_from_str(&desc.date, "%Y-%m-%d").is_ok() {
+        lines.push(tag(1, "premiered", &desc.date));
+    }
+    if !desc.description.is_empty() {
+        lines.push(tag(1, "plot", &desc.description));
+    }
+    if !desc.poster_url.is_empty() {
+        lines.push(format!("  <thumb aspect=\"poster\">{}</thumb>", escape(&full_size_poster(settings, &desc.poster_url))));
+    }
+    if !desc.fanart_url.is_empty() {
+        lines.push("  <fanart>".to_owned());
+        lines.push(tag(2, "thumb", &images::original_url(&desc.fanart_url)));
+        lines.push("  </fanart>".to_owned());
+    }
+    for actor in desc.actors.iter().filter(|a| !a.name.is_empty()) {
+        lines.push("  <actor>".to_owned());
+        lines.push(tag(2, "name", &actor.name));
+        if !actor.role.is_empty() {
+            lines.push(tag(2, "role", &actor.role));
+        }
+        lines.push("  </actor>".to_owned());
+    }
+    lines.push(format!("</{}>", root));
+
+    lines.join("\n") + "\n"
+}
+
+fn tag(indent: usize, name: &str, value: &str) -> String {
+    format!("{}<{}>{}</{}>", "  ".repeat(indent), name, escape(value), name)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// files the user or a previous import already put there are kept as they are
+fn is_new(path: &Path) -> bool {
+    if path.exists() {
+        info!("metadata file {} already exists, keeping it", path.to_string_lossy());
+        return false;
+    }
+    true
+}
+
+async fn download(client: &reqwest::Client, url: &str, path: &Path, max_bytes: u64) -> eyre::Result<()> {
+    let resp = client.get(url).send().await?.error_for_status()?;
+    let bytes = images::read_body(resp, max_bytes).await?;
+
+    let partial = files::partial_path(path);
+    let res = match tokio::fs::write(&partial, &bytes).await {
+        Ok(_) => tokio::fs::rename(&partial, path).await,
+        Err(e) => Err(e),
+    };
+    if res.is_err() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+    res?;
+
+    info!("downloaded artwork {} to {}", url, path.to_string_lossy());
+    Ok(())
+}
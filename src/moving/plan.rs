@@ -0,0 +1,223 @@
+use std::{collections::{BTreeSet, HashMap}, fs, path::{Path, PathBuf}};
+
+use eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{config::Settings, files, trash};
+
+use super::{journal::MoveJournalEntry, conflict::ConflictStrategy, metadata::MediaMetadata, subs_import, ImportMode};
+
+const ORIGINAL_SUB_EXT: &str = "original";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct PlannedMove {
+    pub src: String,
+    pub dest: String,
+}
+
+impl PlannedMove {
+    pub fn new(src: &Path, dest: &Path) -> Self {
+        PlannedMove {
+            src: src.to_string_lossy().into_owned(),
+            dest: dest.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MoveProgress {
+    pub bytes_done: u64,
+    pub current_file: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct MediaMovePlan {
+    #[serde(rename(serialize = "importMode", deserialize = "importMode"))]
+    #[schema(rename = "importMode")]
+    pub import_mode: ImportMode,
+    pub videos: Vec<PlannedMove>,
+    pub extras: Vec<PlannedMove>,
+    pub subs: Vec<PlannedMove>,
+    pub deleted: Vec<String>,
+    #[serde(rename(serialize = "dirsToCreate", deserialize = "dirsToCreate"))]
+    #[schema(rename = "dirsToCreate")]
+    pub dirs_to_create: Vec<String>,
+    #[serde(rename(serialize = "dirsToDelete", deserialize = "dirsToDelete"))]
+    #[schema(rename = "dirsToDelete")]
+    pub dirs_to_delete: Vec<String>,
+    pub replaced: Vec<String>,
+    pub skipped: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub metadata: Option<MediaMetadata>,
+}
+
+impl MediaMovePlan {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    pub fn moves(&self) -> impl Iterator<Item = &PlannedMove> {
+        self.videos.iter().chain(self.extras.iter()).chain(self.subs.iter())
+    }
+
+    pub fn is_replaced(&self, path: &Path) -> bool {
+        self.replaced.iter().any(|r| path.starts_with(r))
+    }
+
+    pub fn accepts_dest(&mut self, dest: &Path, on_conflict: ConflictStrategy) -> bool {
+        if !dest.exists() || self.is_replaced(dest) {
+            return true;
+        }
+
+        let msg = format!("destination file already exists: {}", dest.to_string_lossy());
+        match on_conflict {
+            ConflictStrategy::SKIP | ConflictStrategy::MERGE => {
+                self.skipped.push(msg);
+                false
+            },
+            _ => {
+                self.conflicts.push(msg);
+                true
+            },
+        }
+    }
+
+    pub fn collect_dirs_to_create(&mut self) {
+        let dirs: BTreeSet<String> = self.moves()
+            .filter_map(|m| Path::new(&m.dest).parent())
+            .filter(|p| !p.exists() || self.is_replaced(p))
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        self.dirs_to_create = dirs.into_iter().collect();
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.moves()
+            .map(|m| fs::metadata(&m.src).map(|d| d.len()).unwrap_or_default())
+            .sum()
+    }
+
+    // only files crossing filesystems take up new space, renames and hardlinks don't
+    pub fn check_free_space(&self, settings: &Settings) -> eyre::Result<()> {
+        let mut needed: HashMap<PathBuf, u64> = HashMap::new();
+        for planned in self.moves() {
+            let (src, dest) = (Path::new(&planned.src), Path::new(&planned.dest));
+            let size = match fs::metadata(src) {
+                Ok(data) => data.len(),
+                Err(_) => continue,
+            };
+            if files::same_filesystem(src, dest) {
+                continue;
+            }
+
+            let dest_dir = dest.ancestors().find(|p| p.exists()).unwrap_or(dest);
+            let dir = needed.keys().find(|dir| files::same_filesystem(dir, dest_dir)).cloned()
+                .unwrap_or_else(|| dest_dir.to_path_buf());
+            *needed.entry(dir).or_default() += size;
+        }
+
+        for (dir, bytes) in needed {
+            let free = files::free_space(&dir)?;
+            let reserve = settings.mv.free_space_reserve_bytes;
+            if free < bytes.saturating_add(reserve) {
+                return Err(eyre!("not enough free space on {}: media needs {} bytes and {} bytes are kept in reserve, but only {} bytes are free",
+                    dir.to_string_lossy(), bytes, reserve, free));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute(&self, settings: &Settings, journal: &mut MoveJournalEntry) -> eyre::Result<()> {
+        self.execute_with_progress(settings, journal, &mut |_| ())
+    }
+
+    pub fn execute_with_progress(&self, settings: &Settings, journal: &mut MoveJournalEntry, 
+            progress: &mut dyn FnMut(MoveProgress)) -> eyre::Result<()> {
+        if self.has_conflicts() {
+            let msg = self.conflicts.join(", ");
+            warn!(msg);
+            return Err(eyre!(msg));
+        }
+        self.check_free_space(settings)?;
+
+        journal.import_mode = self.import_mode;
+        for replaced in &self.replaced {
+            info!("moving replaced media {} to trash", replaced);
+            journal.record_replaced(trash::move_to_trash(settings, Path::new(replaced))?);
+        }
+
+        let mut bytes_done = 0;
+        let subs_start = self.videos.len() + self.extras.len();
+        for (index, planned) in self.moves().enumerate() {
+            let src = Path::new(&planned.src);
+            let size = fs::metadata(src).map(|d| d.len()).unwrap_or_default();
+
+            let mut report = |copied| 
+                progress(MoveProgress { bytes_done: bytes_done + copied, current_file: Some(planned.src.clone()) });
+            match self.import_mode {
+                ImportMode::MOVE => files::move_files_with_progress(src, Path::new(&planned.dest), settings.mv.verify_checksum, &mut report)?,
+                ImportMode::HARDLINK => files::link_files_with_progress(src, Path::new(&planned.dest), settings.mv.verify_checksum, &mut report)?,
+            }
+            journal.record_move(planned);
+
+            if index >= subs_start {
+                self.process_sub(settings, src, Path::new(&planned.dest), journal)?;
+            }
+
+            bytes_done += size;
+            progress(MoveProgress { bytes_done, current_file: Some(planned.src.clone()) });
+        }
+
+        for deleted in &self.deleted {
+            if self.dirs_to_delete.iter().any(|dir| Path::new(deleted).starts_with(dir)) {
+                continue;
+            }
+            info!("removing release file {}", deleted);
+            journal.record_trashed(trash::move_to_trash(settings, Path::new(deleted))?);
+        }
+
+        for dir in &self.dirs_to_delete {
+            info!("cleaning media src dir {}", dir);
+            journal.record_trashed(trash::move_to_trash(settings, Path::new(dir))?);
+        }
+
+        Ok(())
+    }
+
+    // moved subs keep their original content in trash for undo, subs that fail converting get their source extension back
+    fn process_sub(&self, settings: &Settings, src: &Path, dest: &Path, journal: &mut MoveJournalEntry) -> eyre::Result<()> {
+        if !subs_import::is_enabled(settings) {
+            return Ok(());
+        }
+
+        // the original is only kept for moves, once the source is gone it's the only copy of the untouched sub
+        let original = dest.with_extension(ORIGINAL_SUB_EXT);
+        let keep_original = (self.import_mode == ImportMode::MOVE).then_some(original.as_path());
+
+        let src_ext = src.extension().unwrap_or_default().to_string_lossy().into_owned();
+        match subs_import::process(settings, &src_ext, dest, keep_original) {
+            Ok(true) if keep_original.is_some() => {
+                journal.record_rewritten(trash::move_to_trash_as(settings, &original, src)?);
+            },
+            Ok(_) => (),
+            Err(e) => {
+                warn!("could not process imported sub {}: {:?}", dest.to_string_lossy(), e);
+                if dest.extension().unwrap_or_default().to_string_lossy() != src_ext {
+                    let renamed = dest.with_extension(&src_ext);
+                    if renamed.exists() {
+                        return Err(eyre!("could not name unconverted sub {} back to {}, path is already taken",
+                            dest.to_string_lossy(), renamed.to_string_lossy()));
+                    }
+                    fs::rename(dest, &renamed)
+                        .wrap_err_with(|| format!("could not name unconverted sub {} back to {}", dest.to_string_lossy(), renamed.to_string_lossy()))?;
+                    journal.record_renamed(&dest.to_string_lossy(), &renamed);
+                    info!("kept unconverted sub as {}", renamed.to_string_lossy());
+                }
+            },
+        }
+        Ok(())
+    }
+}
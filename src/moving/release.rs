@@ -0,0 +1,93 @@
+use std::{ffi::OsStr, path::Path};
+
+use eyre::Context;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::config::Settings;
+
+pub const EXTRAS_DIR: &str = "Extras";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseFileKind {
+    VIDEO,
+    EXTRA,
+    SAMPLE,
+    SUBTITLE,
+    METADATA,
+    JUNK,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseFileAction {
+    EXTRAS,
+    DELETE,
+    IGNORE,
+}
+
+pub fn action_for(settings: &Settings, kind: ReleaseFileKind) -> Option<ReleaseFileAction> {
+    let actions = &settings.mv.release_actions;
+    match kind {
+        ReleaseFileKind::EXTRA => Some(actions.extra),
+        ReleaseFileKind::SAMPLE => Some(actions.sample),
+        ReleaseFileKind::METADATA => Some(actions.metadata),
+        ReleaseFileKind::JUNK => Some(actions.junk),
+        ReleaseFileKind::VIDEO | ReleaseFileKind::SUBTITLE => None,
+    }
+}
+
+pub struct ReleaseClassifier {
+    sample_regex: Regex,
+    extra_dir_regex: Regex,
+    extra_suffix_regex: Regex,
+}
+
+impl ReleaseClassifier {
+    // samples are told apart with the same regex search hides them with
+    pub fn new(settings: &Settings) -> eyre::Result<Self> {
+        Ok(ReleaseClassifier {
+            sample_regex: Regex::new(&settings.search.sample_regex)
+                .wrap_err_with(|| format!("invalid search sample regex {}", &settings.search.sample_regex))?,
+            extra_dir_regex: Regex::new(r"(?i)^(featurettes?|behind[ ._-]?the[ ._-]?scenes|deleted[ ._-]?scenes|interviews|trailers|extras|bonus|making[ ._-]?of|shorts|other)$").unwrap(),
+            extra_suffix_regex: Regex::new(r"(?i)-(trailer|featurette|behindthescenes|deleted|interview|scene|short|other)$").unwrap(),
+        })
+    }
+
+    pub fn classify(&self, settings: &Settings, root: &Path, file: &Path, selected_video: bool) -> ReleaseFileKind {
+        let ext = file.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        if has_ext(&settings.mv.metadata_ext, &ext) {
+            return ReleaseFileKind::METADATA;
+        }
+        if has_ext(&settings.mv.subs_ext, &ext) {
+            return ReleaseFileKind::SUBTITLE;
+        }
+        if !selected_video && !is_video(settings, file) {
+            return ReleaseFileKind::JUNK;
+        }
+
+        let relative = file.strip_prefix(root).unwrap_or(file);
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        let mut dirs = relative.parent().into_iter().flat_map(|p| p.iter()).map(|d| d.to_string_lossy());
+
+        // a video the user picked is never thrown away as a sample, whatever its name
+        if !selected_video && self.sample_regex.is_match(&relative.to_string_lossy()) {
+            ReleaseFileKind::SAMPLE
+        } else if self.extra_suffix_regex.is_match(&stem) || dirs.any(|d| self.extra_dir_regex.is_match(&d)) {
+            ReleaseFileKind::EXTRA
+        } else {
+            ReleaseFileKind::VIDEO
+        }
+    }
+}
+
+fn has_ext(exts: &[String], ext: &str) -> bool {
+    exts.iter().any(|e| OsStr::new(e).eq_ignore_ascii_case(ext))
+}
+
+pub fn is_video(settings: &Settings, file: &Path) -> bool {
+    match infer::get_from_path(file) {
+        Ok(Some(ftype)) => ftype.mime_type().starts_with("video/")
+            || settings.search.video_mime_types.iter().any(|m| m == ftype.mime_type()),
+        _ => false,
+    }
+}
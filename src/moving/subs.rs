@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fs::File, io::Read, path::{Path, PathBuf}};
+
+use regex::Regex;
+
+use crate::episode::EpisodeParser;
+
+use super::plan::PlannedMove;
+
+const CONTENT_SAMPLE_BYTES: u64 = 64 * 1024;
+const CONTENT_MIN_HITS: usize = 5;
+
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("en", &["eng", "english"]),
+    ("ro", &["rum", "ron", "romanian", "romana"]),
+    ("fr", &["fre", "fra", "french", "francais"]),
+    ("de", &["ger", "deu", "german", "deutsch"]),
+    ("es", &["spa", "spanish", "espanol"]),
+    ("it", &["ita", "italian", "italiano"]),
+    ("pt", &["por", "portuguese", "portugues", "brazilian"]),
+    ("nl", &["dut", "nld", "dutch"]),
+    ("ru", &["rus", "russian"]),
+    ("hu", &["hun", "hungarian"]),
+    ("pl", &["pol", "polish"]),
+    ("sv", &["swe", "swedish"]),
+];
+
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "you", "what", "that", "is", "this", "are", "have", "with"]),
+    ("ro", &["și", "si", "nu", "este", "că", "ca", "pe", "ce", "sunt", "în", "asta"]),
+    ("fr", &["le", "les", "et", "est", "vous", "je", "pas", "une", "ce", "qui"]),
+    ("de", &["der", "die", "und", "ist", "nicht", "ich", "das", "sie", "ein", "du"]),
+    ("es", &["el", "que", "los", "por", "una", "pero", "está", "qué", "muy", "yo"]),
+    ("it", &["il", "che", "non", "di", "è", "per", "sono", "della", "questo", "io"]),
+    ("pt", &["não", "você", "um", "uma", "isso", "está", "para", "com", "eu", "os"]),
+    ("nl", &["het", "een", "niet", "ik", "je", "dat", "van", "wat", "zijn", "maar"]),
+];
+
+const FORCED_TOKENS: &[&str] = &["forced", "foreign"];
+const SDH_TOKENS: &[&str] = &["sdh"];
+// too common in titles to trust anywhere but the trailing tags, like "Hi.Score.Girl"
+const TAG_SDH_TOKENS: &[&str] = &["hi", "cc"];
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SubInfo {
+    pub language: Option<String>,
+    pub forced: bool,
+    pub sdh: bool,
+}
+
+pub struct SubsNamer {
+    token_regex: Regex,
+    word_regex: Regex,
+    episode_parser: EpisodeParser,
+}
+
+impl SubsNamer {
+    pub fn new() -> Self {
+        SubsNamer {
+            token_regex: Regex::new(r"[^\p{L}\p{N}]+").unwrap(),
+            word_regex: Regex::new(r"\p{L}+").unwrap(),
+            episode_parser: EpisodeParser::new(),
+        }
+    }
+
+    pub fn detect(&self, sub: &Path) -> SubInfo {
+        let mut info = self.detect_from_name(sub);
+        if info.language.is_none() {
+            info.language = self.detect_from_content(sub);
+        }
+        info
+    }
+
+    fn detect_from_name(&self, sub: &Path) -> SubInfo {
+        let stem = sub.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+        let mut info = SubInfo::default();
+        let mut last_token = true;
+        let mut in_tags = true;
+
+        for token in self.token_regex.split(&stem).filter(|t| !t.is_empty()).collect::<Vec<&str>>().into_iter().rev() {
+            if FORCED_TOKENS.contains(&token) {
+                info.forced = true;
+                continue;
+            }
+            if SDH_TOKENS.contains(&token) || (in_tags && TAG_SDH_TOKENS.contains(&token)) {
+                info.sdh = true;
+                continue;
+            }
+
+            let language = LANGUAGES.iter()
+                .find(|(code, names)| names.contains(&token) || (last_token && *code == token))
+                .map(|(code, _)| code.to_string());
+            in_tags = in_tags && language.is_some();
+            if info.language.is_none() {
+                info.language = language;
+            }
+            last_token = false;
+        }
+
+        info
+    }
+
+    fn detect_from_content(&self, sub: &Path) -> Option<String> {
+        let mut content = vec![];
+        File::open(sub).ok()?.take(CONTENT_SAMPLE_BYTES).read_to_end(&mut content).ok()?;
+        let text = String::from_utf8_lossy(&content).to_lowercase();
+
+        let mut hits: HashMap<&str, usize> = HashMap::new();
+        for word in self.word_regex.find_iter(&text) {
+            for (code, words) in STOPWORDS {
+                if words.contains(&word.as_str()) {
+                    *hits.entry(code).or_default() += 1;
+                }
+            }
+        }
+
+        hits.into_iter()
+            .filter(|(_, count)| *count >= CONTENT_MIN_HITS)
+            .max_by_key(|(_, count)| *count)
+            .map(|(code, _)| code.to_owned())
+    }
+
+    pub fn match_episode<'a>(&self, sub: &Path, videos: &'a [PlannedMove]) -> Option<&'a PlannedMove> {
+        let (season, episode) = self.episode_parser.parse(sub);
+
+        if let Some(episode) = episode {
+            let matching: Vec<&PlannedMove> = videos.iter()
+                .filter(|v| {
+                    let (video_season, video_episode) = self.episode_parser.parse(Path::new(&v.src));
+                    video_episode == Some(episode) && (season.is_none() || video_season.is_none() || video_season == season)
+                })
+                .collect();
+            if matching.len() == 1 {
+                return Some(matching[0]);
+            }
+        }
+
+        match videos.len() {
+            1 => videos.first(),
+            _ => None,
+        }
+    }
+
+    pub fn dest(&self, sub: &Path, ext: &str, video_dest: &Path, taken: &[PlannedMove]) -> PathBuf {
+        let info = self.detect(sub);
+        let video_stem = video_dest.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+
+        let mut suffix = String::new();
+        if let Some(language) = &info.language {
+            suffix.push_str(&format!(".{}", language));
+        }
+        if info.forced {
+            suffix.push_str(".forced");
+        } else if info.sdh {
+            suffix.push_str(".sdh");
+        }
+
+        let mut dest = video_dest.with_file_name(format!("{}{}.{}", video_stem, suffix, ext));
+        let mut index = 2;
+        while taken.iter().any(|t| Path::new(&t.dest) == dest) {
+            dest = video_dest.with_file_name(format!("{}.{}{}.{}", video_stem, index, suffix, ext));
+            index += 1;
+        }
+        dest
+    }
+}
+
+impl Default for SubsNamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::moving::plan::PlannedMove;
+
+    use super::{SubInfo, SubsNamer};
+
+    #[test]
+    fn sub_language_detected_from_name() {
+        let namer = SubsNamer::new();
+
+        assert_eq!(Some("en".to_owned()), namer.detect_from_name(Path::new("Subs/2_English.srt")).language);
+        assert_eq!(Some("ro".to_owned()), namer.detect_from_name(Path::new("movie.rum.srt")).language);
+        assert_eq!(SubInfo { language: Some("en".to_owned()), forced: true, sdh: false },
+            namer.detect_from_name(Path::new("movie.en.forced.srt")));
+        assert_eq!(SubInfo { language: Some("fr".to_owned()), forced: false, sdh: true },
+            namer.detect_from_name(Path::new("movie.french.SDH.srt")));
+        assert_eq!(None, namer.detect_from_name(Path::new("The.It.Crowd.S01E01.srt")).language);
+    }
+
+    #[test]
+    fn sub_language_detected_from_content() {
+        let namer = SubsNamer::new();
+        let sub = std::env::temp_dir().join(format!("{:032x}.srt", rand::random::<u128>()));
+        std::fs::write(&sub, "1\n00:00:01,000 --> 00:00:02,000\nWhat is this and where are you going with the car?\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\nI have the keys, you have the map.\n").unwrap();
+
+        assert_eq!(Some("en".to_owned()), namer.detect(&sub).language);
+        std::fs::remove_file(&sub).unwrap();
+    }
+
+    #[test]
+    fn sub_matched_to_episode() {
+        let namer = SubsNamer::new();
+        let videos = vec![
+            PlannedMove::new(Path::new("/dl/show/Show.S01E01.mkv"), Path::new("/tv/show/Show.S01E01.mkv")),
+            PlannedMove::new(Path::new("/dl/show/Show.S01E02.mkv"), Path::new("/tv/show/Show.S01E02.mkv")),
+        ];
+
+        let matched = namer.match_episode(Path::new("/dl/show/Subs/Show.S01E02/2_English.srt"), &videos);
+        assert_eq!(Some(&videos[1]), matched);
+        assert_eq!(Some(&videos[0]), namer.match_episode(Path::new("/dl/show/Subs/1x01.srt"), &videos));
+        assert_eq!(None, namer.match_episode(Path::new("/dl/show/Subs/English.srt"), &videos));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use eyre::{eyre, Context};
+use regex::Regex;
+use tracing::info;
+
+use crate::config::Settings;
+
+const SRT_EXT: &str = "srt";
+const MICRODVD_EXT: &str = "sub";
+const SSA_EXTS: &[&str] = &["ssa", "ass"];
+const PROCESSED_EXT: &str = "processing";
+
+const UNLIKELY_CHARS: &str = "¤¦§¨©ª«¬®¯°±²³´µ¶·¸¹º»¼½¾þðÞÐ×÷ŕŔĺĹˇ˘˛˝";
+
+static MICRODVD_LINE_REGEX: OnceLock<Regex> = OnceLock::new();
+static TAG_REGEX: OnceLock<Regex> = OnceLock::new();
+
+pub fn target_ext(settings: &Settings, sub: &Path) -> String {
+    let ext = sub.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    if settings.mv.subs_convert_to_srt && is_convertible(sub, &ext) {
+        return SRT_EXT.to_owned();
+    }
+    sub.extension().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn is_convertible(sub: &Path, ext: &str) -> bool {
+    match ext {
+        MICRODVD_EXT => fs::read(sub).map(|c| is_microdvd(&String::from_utf8_lossy(&c))).unwrap_or(false),
+        e => SSA_EXTS.contains(&e),
+    }
+}
+
+fn is_microdvd(content: &str) -> bool {
+    content.lines()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l.trim_start_matches('\u{feff}').starts_with('{') && l.contains("}{"))
+        .unwrap_or(false)
+}
+
+pub fn is_enabled(settings: &Settings) -> bool {
+    settings.mv.subs_normalize_encoding || settings.mv.subs_convert_to_srt
+}
+
+// returns whether the content of dest was rewritten, the replaced content is moved to original when given
+pub fn process(settings: &Settings, src_ext: &str, dest: &Path, original: Option<&Path>) -> eyre::Result<bool> {
+    if !is_enabled(settings) {
+        return Ok(false);
+    }
+
+    let bytes = fs::read(dest).wrap_err_with(|| format!("could not read sub {}", dest.to_string_lossy()))?;
+    let (mut content, encoding) = match decode(&bytes, &settings.mv.subs_encodings) {
+        Some(decoded) => decoded,
+        None => {
+            info!("sub {} looks binary, skipping import processing", dest.to_string_lossy());
+            return Ok(false);
+        },
+    };
+
+    let src_ext = src_ext.to_lowercase();
+    let is_srt_dest = dest.extension().map(|e| e == SRT_EXT).unwrap_or(false);
+    if settings.mv.subs_convert_to_srt && is_srt_dest && src_ext != SRT_EXT {
+        content = match src_ext.as_str() {
+            MICRODVD_EXT => microdvd_to_srt(&content, settings.mv.subs_default_fps)?,
+            e if SSA_EXTS.contains(&e) => ssa_to_srt(&content)?,
+            e => return Err(eyre!("cannot convert sub format {} to srt", e)),
+        };
+        info!("converted sub {} from {} to srt", dest.to_string_lossy(), src_ext);
+    } else if encoding == UTF_8 || !settings.mv.subs_normalize_encoding {
+        return Ok(false);
+    }
+
+    info!("rewriting sub {} from {} as UTF-8", dest.to_string_lossy(), encoding.name());
+
+    // written next to dest and renamed over it, so a hardlinked source keeps its original content
+    let processed = dest.with_extension(PROCESSED_EXT);
+    fs::write(&processed, content.trim_start_matches('\u{feff}'))
+        .wrap_err_with(|| format!("could not rewrite sub {}", dest.to_string_lossy()))?;
+
+    if let Some(original) = original {
+        fs::rename(dest, original)
+            .wrap_err_with(|| format!("could not keep original content of sub {}", dest.to_string_lossy()))?;
+    }
+    if let Err(e) = fs::rename(&processed, dest) {
+        if let Some(original) = original {
+            fs::rename(original, dest)
+                .wrap_err_with(|| format!("could not restore original content of sub {}", dest.to_string_lossy()))?;
+        }
+        return Err(e).wrap_err_with(|| format!("could not rewrite sub {}", dest.to_string_lossy()));
+    }
+    Ok(true)
+}
+
+pub fn decode(bytes: &[u8], fallback_encodings: &[String]) -> Option<(String, &'static Encoding)> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (content, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Some((content.into_owned(), encoding));
+    }
+
+    if let Ok(content) = std::str::from_utf8(bytes) {
+        return match content.contains('\0') {
+            true => utf16_without_bom(bytes),
+            false => Some((content.to_owned(), UTF_8)),
+        };
+    }
+
+    if bytes.contains(&0) {
+        return utf16_without_bom(bytes);
+    }
+
+    fallback_encodings.iter()
+        .filter_map(|label| Encoding::for_label(label.as_bytes()))
+        .map(|encoding| {
+            let (content, _, _) = encoding.decode(bytes);
+            let score = content.chars().filter(|c| is_unlikely(*c)).count();
+            (score, content.into_owned(), encoding)
+        })
+        .min_by_key(|(score, _, _)| *score)
+        .map(|(_, content, encoding)| (content, encoding))
+}
+
+fn utf16_without_bom(bytes: &[u8]) -> Option<(String, &'static Encoding)> {
+    let even_zeros = bytes.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    let encoding = match (even_zeros, odd_zeros) {
+        (e, o) if o > bytes.len() / 4 && e == 0 => UTF_16LE,
+        (e, o) if e > bytes.len() / 4 && o == 0 => UTF_16BE,
+        _ => return None,
+    };
+
+    let (content, had_errors) = encoding.decode_without_bom_handling(bytes);
+    match had_errors {
+        true => None,
+        false => Some((content.into_owned(), encoding)),
+    }
+}
+
+fn is_unlikely(c: char) -> bool {
+    ('\u{80}'..='\u{9f}').contains(&c) || c == char::REPLACEMENT_CHARACTER || UNLIKELY_CHARS.contains(c)
+}
+
+pub fn microdvd_to_srt(content: &str, default_fps: f64) -> eyre::Result<String> {
+    let line_regex = MICRODVD_LINE_REGEX.get_or_init(|| Regex::new(r"^\{(\d+)\}\{(\d*)\}(.*)$").unwrap());
+    let tag_regex = tag_regex();
+
+    let mut fps = default_fps;
+    let mut cues = vec![];
+    for line in content.lines().map(|l| l.trim().trim_start_matches('\u{feff}')) {
+        let c = match line_regex.captures(line) {
+            Some(c) => c,
+            None => continue,
+        };
+
+        let start: u64 = c[1].parse()?;
+        let end: u64 = c[2].parse().unwrap_or(start);
+        let text = tag_regex.replace_all(&c[3], "").replace('|', "\n");
+
+        if start <= 1 && end <= 1 {
+            if let Ok(declared_fps) = text.trim().parse::<f64>() {
+                fps = declared_fps;
+                continue;
+            }
+        }
+
+        cues.push((frames_to_millis(start, fps), frames_to_millis(end, fps), text));
+    }
+
+    if cues.is_empty() {
+        return Err(eyre!("no MicroDVD cues found"));
+    }
+
+    Ok(to_srt(cues))
+}
+
+fn frames_to_millis(frames: u64, fps: f64) -> u64 {
+    (frames as f64 * 1000.0 / fps).round() as u64
+}
+
+pub fn ssa_to_srt(content: &str) -> eyre::Result<String> {
+    let tag_regex = tag_regex();
+
+    let mut in_events = false;
+    let mut format: Vec<String> = vec![];
+    let mut cues = vec![];
+    for line in content.lines().map(|l| l.trim().trim_start_matches('\u{feff}')) {
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+
+        if let Some(fields) = line.strip_prefix("Format:") {
+            format = fields.split(',').map(|f| f.trim().to_lowercase()).collect();
+            continue;
+        }
+
+        let dialogue = match line.strip_prefix("Dialogue:") {
+            Some(dialogue) if !format.is_empty() => dialogue,
+            _ => continue,
+        };
+
+        let values: Vec<&str> = dialogue.splitn(format.len(), ',').map(|v| v.trim()).collect();
+        let field = |name: &str| format.iter().position(|f| f == name).and_then(|i| values.get(i)).copied();
+
+        let (start, end, text) = match (field("start"), field("end"), field("text")) {
+            (Some(start), Some(end), Some(text)) => (parse_ssa_time(start)?, parse_ssa_time(end)?, text),
+            _ => continue,
+        };
+
+        let text = tag_regex.replace_all(text, "")
+            .replace("\\N", "\n")
+            .replace("\\n", "\n")
+            .replace("\\h", " ");
+        cues.push((start, end, text));
+    }
+
+    if cues.is_empty() {
+        return Err(eyre!("no SSA dialogue events found"));
+    }
+
+    cues.sort_by_key(|(start, _, _)| *start);
+    Ok(to_srt(cues))
+}
+
+fn tag_regex() -> &'static Regex {
+    TAG_REGEX.get_or_init(|| Regex::new(r"\{[^}]*\}").unwrap())
+}
+
+fn parse_ssa_time(time: &str) -> eyre::Result<u64> {
+    let parts: Vec<&str> = time.split([':', '.']).collect();
+    if parts.len() != 4 {
+        return Err(eyre!("invalid SSA time {}", time));
+    }
+
+    let hours: u64 = parts[0].parse()?;
+    let minutes: u64 = parts[1].parse()?;
+    let seconds: u64 = parts[2].parse()?;
+    let centis: u64 = parts[3].parse()?;
+    Ok(((hours * 60 + minutes) * 60 + seconds) * 1000 + centis * 10)
+}
+
+fn to_srt(cues: Vec<(u64, u64, String)>) -> String {
+    cues.into_iter()
+        .enumerate()
+        .map(|(i, (start, end, text))| format!("{}\n{} --> {}\n{}\n", i + 1, srt_time(start), srt_time(end), text.trim()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn srt_time(millis: u64) -> String {
+    format!("{:02}:{:02}:{:02},{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{WINDOWS_1250, WINDOWS_1252};
+
+    use super::{decode, microdvd_to_srt, ssa_to_srt};
+
+    #[test]
+    fn sub_encoding_detected() {
+        let fallbacks = vec!["windows-1250".to_owned(), "windows-1252".to_owned()];
+
+        let romanian = "Şi ţie îţi place aşa, ştiu că aş fi venit.";
+        let (bytes, _, _) = WINDOWS_1250.encode(romanian);
+        assert_eq!((romanian.to_owned(), WINDOWS_1250), decode(&bytes, &fallbacks).unwrap());
+
+        let spanish = "¿Qué pasó? ¡Señor, sí! El niño está aquí, à la française.";
+        let (bytes, _, _) = WINDOWS_1252.encode(spanish);
+        assert_eq!((spanish.to_owned(), WINDOWS_1252), decode(&bytes, &fallbacks).unwrap());
+
+        assert_eq!("déjà vu", decode("\u{feff}déjà vu".as_bytes(), &fallbacks).unwrap().0);
+        assert!(decode(&[0x1a, 0x45, 0xdf, 0xa3, 0x00, 0x00, 0x00, 0xff], &fallbacks).is_none());
+    }
+
+    #[test]
+    fn microdvd_converted_to_srt() {
+        let microdvd = "{1}{1}25.000\n{25}{75}Hello|there\n{100}{150}{y:i}Bye\n";
+
+        assert_eq!("1\n00:00:01,000 --> 00:00:03,000\nHello\nthere\n\n2\n00:00:04,000 --> 00:00:06,000\nBye\n",
+            microdvd_to_srt(microdvd, 23.976).unwrap());
+    }
+
+    #[test]
+    fn ssa_converted_to_srt() {
+        let ssa = "[Script Info]\nTitle: test\n\n[Events]\n\
+            Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+            Dialogue: 0,0:00:05.50,0:00:07.00,Default,,0,0,0,,{\\i1}Second{\\i0}, line\\Nbreak\n\
+            Dialogue: 0,0:00:01.00,0:00:02.25,Default,,0,0,0,,First\n";
+
+        assert_eq!("1\n00:00:01,000 --> 00:00:02,250\nFirst\n\n2\n00:00:05,500 --> 00:00:07,000\nSecond, line\nbreak\n",
+            ssa_to_srt(ssa).unwrap());
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::{Component, Path};
+
+use crate::{config::Settings, files, roots, search::MediaFileGroup};
+
+use super::MediaMoveReq;
+
+pub fn validate_move_req(settings: &Settings, req: &MediaMoveReq) -> Vec<(&'static str, String)> {
+    let mut errors = validate_file_group(settings, &req.file_group, ("fileGroup.path", "fileGroup.videos"));
+
+    let name = req.name.as_ref().unwrap_or(&req.file_group.name);
+    // unknown media gets classified later, so it has to fit every library
+    let dest_roots = roots::library_roots(settings, req.media_type);
+    if !is_single_segment(name) {
+        errors.push(("name", format!("media name should be a single folder name: {}", name)));
+    } else {
+        let escapes_root = dest_roots.into_iter()
+            .map(|r| files::canonicalize(Path::new(&r.path)))
+            .any(|r| !files::canonicalize(&r.join(name)).starts_with(&r));
+        if escapes_root {
+            errors.push(("name", format!("media destination is outside the library root: {}", name)));
+        }
+    }
+
+    errors
+}
+
+pub fn validate_file_group(settings: &Settings, file_group: &MediaFileGroup, (path_key, videos_key): (&'static str, &'static str)) -> Vec<(&'static str, String)> {
+    let mut errors = vec![];
+
+    let src = files::canonicalize(Path::new(&file_group.path));
+    if !Path::new(&file_group.path).is_absolute() || roots::downloads_root_of(settings, &src).is_none() {
+        errors.push((path_key, format!("media path is outside the downloads roots: {}", &file_group.path)));
+    }
+
+    for video in &file_group.videos {
+        let is_relative = Path::new(video).components().all(|c| matches!(c, Component::Normal(_)));
+        if !is_relative || video.is_empty() || !files::canonicalize(&src.join(video)).starts_with(&src) {
+            errors.push((videos_key, format!("video should be a relative path inside the media path: {}", video)));
+        }
+    }
+
+    errors
+}
+
+pub fn is_single_segment(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+        && !name.contains(['/', '\\'])
+}
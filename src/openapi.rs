@@ -4,33 +4,83 @@ use utoipa::OpenApi;
 #[openapi(
     paths(
         crate::search::search_media,
+        crate::search::stream_media,
+        crate::search::search_archives,
+        crate::archive::extract_media_archives,
+        crate::classify::classify_media_group,
+        crate::library::library_movies,
+        crate::library::library_tv,
+        crate::library::library_duplicates,
+        crate::library::library_missing_episodes,
+        crate::images::get_image,
         crate::download::downloads_completed,
         crate::command::execute_cmd,
         crate::moving::move_media,
+        crate::moving::preview_media_move,
+        crate::moving::undo_media_move,
+        crate::moving::cleanup_media_sources,
+        crate::moving::submit_move_job,
+        crate::moving::list_move_jobs,
+        crate::moving::get_move_job,
         crate::rename::produce_renames,
+        crate::trash::list_trash,
+        crate::trash::restore_trash_item,
+        crate::trash::empty_trash,
     ),
     components(
         schemas(
             crate::search::MediaFileGroup,
+            crate::search::MediaVideo,
+            crate::quality::MediaQuality,
+            crate::search::ExcludedVideo,
+            crate::search::ExclusionReason,
+            crate::search::MediaArchiveGroup,
+            crate::archive::MediaExtractionResult,
+            crate::classify::MediaClassification,
+            crate::library::LibraryPage,
+            crate::library::LibraryMedia,
+            crate::library::LibrarySeason,
+            crate::library::LibraryDuplicates,
+            crate::library::DuplicateGroup,
+            crate::library::DuplicateCopy,
+            crate::library::MissingEpisodes,
             crate::download::DownloadedMedia,
             crate::command::CommandReq,
             crate::command::CommandResp,
             crate::command::Status,
             crate::moving::MediaMoveReq,
-            crate::moving::MediaMoveError,
+            crate::moving::MediaMoveResult,
+            crate::moving::plan::MediaMovePlan,
+            crate::moving::metadata::MediaMetadata,
+            crate::moving::plan::PlannedMove,
+            crate::moving::job::MoveJob,
+            crate::moving::job::MoveJobPage,
+            crate::moving::job::MoveJobStatus,
+            crate::moving::journal::MoveUndoResult,
+            crate::moving::conflict::ConflictStrategy,
+            crate::moving::ImportMode,
+            crate::moving::MediaCleanupReq,
+            crate::moving::MediaCleanupResult,
             crate::rename::MediaRenameRequest,
             crate::rename::RenamedMediaOptions,
             crate::rename::MediaFileType,
             crate::rename::MediaDescription,
+            crate::rename::MediaActor,
             crate::rename::MediaRenameOrigin,
+            crate::trash::TrashItem,
         ),
     ),
     tags(
         (name = "search", description = "Search media API"),
+        (name = "archive", description = "Archived media API"),
+        (name = "classify", description = "Media classification API"),
+        (name = "library", description = "Media library inventory API"),
         (name = "download", description = "Downloaded media API"),
         (name = "command", description = "Command execution API"),
         (name = "moving", description = "Moving media API"),
         (name = "rename", description = "Renaming media API"),
+        (name = "trash", description = "Trashed media API"),
+        (name = "images", description = "Cached images API"),
     )
 )]
 pub struct ApiDoc;
\ No newline at end of file
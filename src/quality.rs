@@ -0,0 +1,151 @@
+use std::{cmp::Ordering, path::Path};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const RESOLUTIONS: &[(&str, &str)] = &[
+    ("2160p", r"(?i)\b(2160p|4k|uhd)\b"),
+    ("1080p", r"(?i)\b1080[pi]\b"),
+    ("720p", r"(?i)\b720p\b"),
+    ("576p", r"(?i)\b576p\b"),
+    ("480p", r"(?i)\b480p\b"),
+];
+
+const SOURCES: &[(&str, &str)] = &[
+    ("REMUX", r"(?i)\bremux\b"),
+    ("BluRay", r"(?i)\b(blu-?ray|bdrip|brrip|bdremux)\b"),
+    ("WEB-DL", r"(?i)\bweb-?dl\b"),
+    ("WEBRip", r"(?i)\bweb-?rip\b"),
+    ("HDTV", r"(?i)\bhdtv\b"),
+    ("DVDRip", r"(?i)\b(dvdrip|dvd)\b"),
+    ("HDRip", r"(?i)\bhdrip\b"),
+    ("CAM", r"(?i)\b(cam|hdcam|ts|telesync|dvdscr)\b"),
+];
+
+const CODECS: &[(&str, &str)] = &[
+    ("AV1", r"(?i)\bav1\b"),
+    ("HEVC", r"(?i)\b(x265|h ?265|hevc)\b"),
+    ("AVC", r"(?i)\b(x264|h ?264|avc)\b"),
+    ("XviD", r"(?i)\b(xvid|divx)\b"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct MediaQuality {
+    pub resolution: Option<String>,
+    pub source: Option<String>,
+    pub codec: Option<String>,
+}
+
+impl MediaQuality {
+    pub fn label(&self) -> String {
+        [&self.resolution, &self.source]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    pub fn compare(&self, other: &MediaQuality) -> Ordering {
+        rank(RESOLUTIONS, &self.resolution).cmp(&rank(RESOLUTIONS, &other.resolution))
+            .then(rank(SOURCES, &self.source).cmp(&rank(SOURCES, &other.source)))
+    }
+}
+
+fn rank(known: &[(&str, &str)], value: &Option<String>) -> usize {
+    match value {
+        Some(v) => known.len() - known.iter().position(|(k, _)| k == v).unwrap_or(known.len()),
+        None => 0,
+    }
+}
+
+#[derive(Debug)]
+pub struct QualityParser {
+    resolutions: Vec<(&'static str, Regex)>,
+    sources: Vec<(&'static str, Regex)>,
+    codecs: Vec<(&'static str, Regex)>,
+    separators_regex: Regex,
+}
+
+impl QualityParser {
+    pub fn new() -> Self {
+        QualityParser {
+            resolutions: compile(RESOLUTIONS),
+            sources: compile(SOURCES),
+            codecs: compile(CODECS),
+            separators_regex: Regex::new(r"[._\[\]()]").unwrap(),
+        }
+    }
+
+    pub fn parse(&self, name: &str) -> MediaQuality {
+        let name = self.separators_regex.replace_all(name, " ");
+        MediaQuality {
+            resolution: find(&self.resolutions, &name),
+            source: find(&self.sources, &name),
+            codec: find(&self.codecs, &name),
+        }
+    }
+
+    // the extension is not part of the release name, a .ts container would read as a telesync source
+    pub fn parse_file(&self, file: &Path) -> MediaQuality {
+        self.parse(&file.with_extension("").to_string_lossy())
+    }
+}
+
+impl Default for QualityParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn compile(known: &[(&'static str, &str)]) -> Vec<(&'static str, Regex)> {
+    known.iter()
+        .map(|(k, r)| (*k, Regex::new(r).unwrap()))
+        .collect()
+}
+
+fn find(known: &[(&'static str, Regex)], name: &str) -> Option<String> {
+    known.iter()
+        .find(|(_, r)| r.is_match(name))
+        .map(|(k, _)| k.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cmp::Ordering, path::Path};
+
+    use super::QualityParser;
+
+    #[test]
+    fn quality_parsed_and_compared() {
+        let parser = QualityParser::new();
+
+        let uhd = parser.parse("Inception.2010.2160p.UHD.BluRay.x265-GROUP.mkv");
+        let hd = parser.parse("Inception (2010) 720p WEB-DL H.264.mp4");
+        let unknown = parser.parse("Inception.mkv");
+
+        assert_eq!(Some("2160p".to_owned()), uhd.resolution);
+        assert_eq!(Some("BluRay".to_owned()), uhd.source);
+        assert_eq!(Some("HEVC".to_owned()), uhd.codec);
+        assert_eq!("720p WEB-DL", hd.label());
+        assert_eq!(Some("AVC".to_owned()), hd.codec);
+        assert_eq!(Ordering::Greater, uhd.compare(&hd));
+        assert_eq!(Ordering::Greater, hd.compare(&unknown));
+        assert_eq!(Ordering::Equal, unknown.compare(&parser.parse("Inception.avi")));
+    }
+
+    #[test]
+    fn file_extension_not_parsed_as_quality() {
+        let parser = QualityParser::new();
+
+        let ts = parser.parse_file(Path::new("Movie (2020)/Movie.2020.1080p.BluRay.ts"));
+        let no_source = parser.parse_file(Path::new("Movie.2020.1080p.ts"));
+        let cam = parser.parse_file(Path::new("Movie.2020.TS.x264.mkv"));
+
+        assert_eq!(Some("BluRay".to_owned()), ts.source);
+        assert_eq!(Some("1080p".to_owned()), ts.resolution);
+        assert_eq!(None, no_source.source);
+        assert_eq!(Some("CAM".to_owned()), cam.source);
+    }
+}
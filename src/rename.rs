@@ -7,7 +7,7 @@ use serde::{Serialize, Deserialize};
 use tracing::{info, warn};
 use utoipa::ToSchema;
 
-use crate::{http::{self}, config::Settings, db::DbClient, tmdb::TmdbAPI};
+use crate::{http::{self}, classify, config::Settings, images, db::DbClient, search::MediaFileGroup, tmdb::TmdbAPI};
 
 use self::{name::{BaseInfo, NameGenerator}, disk::DiskRenamer, cache::CacheRenamer, external::ExternalRenamer};
 
@@ -16,10 +16,11 @@ pub mod cache;
 pub mod disk;
 pub mod name;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
 pub enum MediaFileType {
     MOVIE,
     TV,
+    #[default]
     UNKNOWN,
 }
 
@@ -88,10 +89,21 @@ pub struct MediaDescription {
     #[serde(rename(serialize = "posterUrl", deserialize = "posterUrl"))]
     #[schema(rename = "posterUrl")]
     pub poster_url: String,
+    #[serde(default, rename(serialize = "fanartUrl", deserialize = "fanartUrl"))]
+    #[schema(rename = "fanartUrl")]
+    pub fanart_url: String,
     pub title: String,
     pub date: String,
     pub description: String,
     pub cast: Vec<String>,
+    #[serde(default)]
+    pub actors: Vec<MediaActor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct MediaActor {
+    pub name: String,
+    pub role: String,
 }
 
 #[async_trait]
@@ -110,6 +122,7 @@ enum RenamerKind {
 }
 
 struct RenamersContext {
+    settings: Arc<Settings>,
     renamers: BTreeMap<RenamerOrder, RenamerKind>,
     generator: NameGenerator,
 }
@@ -123,7 +136,8 @@ impl RenamersContext {
 
         RenamersContext { 
             renamers, 
-            generator: NameGenerator::new(settings), 
+            generator: NameGenerator::new(settings.clone()), 
+            settings,
         }
     }
 }
@@ -142,9 +156,25 @@ pub fn router(settings: Arc<Settings>, db_client: DbClient) -> Router {
 async fn produce_renames(State(rename_ctx): State<Arc<RenamersContext>>, 
         Json(req): Json<MediaRenameRequest>) -> http::Result<Json<RenamedMediaOptions>> {
     info!("produce_renames request received with payload: {:?}", req);
+
+    let mut media_type = req.media_type;
+    if media_type == MediaFileType::UNKNOWN {
+        let file_group = MediaFileGroup { name: req.name.clone(), ..Default::default() };
+        let classification = classify::classify_media(rename_ctx.settings.clone(), file_group).await?;
+        // renamers search every media type for UNKNOWN, so an unsure guess is dropped rather than narrowing them
+        if classification.confidence >= rename_ctx.settings.classify.move_min_confidence {
+            media_type = classification.media_type;
+        } else {
+            info!("ignoring classified media type {:?} at {}% for {}", classification.media_type, classification.confidence, &req.name);
+        }
+    }
     
     let base_info = rename_ctx.generator.generate_base_info(req.name);
-    let options = produce_rename_options(base_info, &rename_ctx.renamers, req.media_type, &rename_ctx.generator).await;
+    let mut options = produce_rename_options(base_info, &rename_ctx.renamers, media_type, &rename_ctx.generator).await;
+    for desc in options.descriptions.iter_mut() {
+        desc.poster_url = images::proxy_url(&rename_ctx.settings, &desc.poster_url);
+        desc.fanart_url = images::proxy_url(&rename_ctx.settings, &desc.fanart_url);
+    }
 
     Ok(Json(options))
 }
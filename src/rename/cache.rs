@@ -4,17 +4,19 @@ use serde::Serialize;
 
 use crate::db::DbClient;
 
-use super::{Renamer, name::BaseInfo, RenamedMediaOptions, MediaFileType, MediaDescription, MediaRenameOrigin};
+use super::{Renamer, name::BaseInfo, RenamedMediaOptions, MediaFileType, MediaDescription, MediaActor, MediaRenameOrigin};
 
 #[derive(Serialize, Debug)]
 pub struct OnlineCacheItem {
     pub search_name: String,
     pub search_year: Option<i32>,
     pub cover_path: String,
+    pub fanart_path: String,
     pub title: String,
     pub date: i64,
     pub description: String,
     pub cast: Vec<String>,
+    pub actors: Vec<MediaActor>,
     pub media_type: MediaFileType,
 }
 
@@ -43,10 +45,12 @@ impl Renamer for CacheRenamer {
         let descs: Vec<MediaDescription> = items.into_iter()
             .map(|i| MediaDescription { 
                 poster_url: i.cover_path, 
+                fanart_url: i.fanart_path, 
                 title: i.title, 
                 date: to_date(i.date), 
                 description: i.description, 
-                cast: i.cast 
+                cast: i.cast, 
+                actors: i.actors, 
             })
             .collect();
 
@@ -1,4 +1,4 @@
-use std::{sync::Arc, path::Path, cmp::max};
+use std::{sync::Arc, path::PathBuf, cmp::max, collections::HashSet};
 
 use async_trait::async_trait;
 use edit_distance::edit_distance;
@@ -7,7 +7,7 @@ use regex::Regex;
 use tracing::info;
 use walkdir::DirEntry;
 
-use crate::{config::Settings, files};
+use crate::{config::Settings, files, roots};
 
 use super::{Renamer, RenamedMediaOptions, name::{BaseInfo, NameGenerator}, MediaFileType, MediaRenameOrigin};
 
@@ -50,25 +50,27 @@ impl DiskRenamer {
 #[async_trait]
 impl Renamer for DiskRenamer {
     async fn find_options(&self, base_info: &BaseInfo, media_type: MediaFileType) -> eyre::Result<Option<RenamedMediaOptions>> {
-        let media_path = Path::new(match media_type {
-            MediaFileType::MOVIE => &self.settings.filesystem.movies_path,
-            MediaFileType::TV => &self.settings.filesystem.tv_path,
-            MediaFileType::UNKNOWN => {
-                return Err(eyre!("unknown media type provided for base info {:?}", base_info));
-            },
-        });
-
-        let mut name_variants = files::walk_dirs(media_path, self.settings.rename.max_depth)?
-            .into_iter()
-            .filter(|d| media_path != d.path())
-            .map(|d| DiskPath::new(d, base_info.name(), &self.release_date_regex))
-            .filter(|d| exclude_unsimilar(d, self.settings.rename.similarity_percent, base_info.name()))
-            .collect::<Vec<DiskPath>>();
+        if media_type == MediaFileType::UNKNOWN {
+            return Err(eyre!("unknown media type provided for base info {:?}", base_info));
+        }
+
+        let mut name_variants = vec![];
+        for root in roots::library_roots(&self.settings, media_type) {
+            let media_path = PathBuf::from(&root.path);
+            name_variants.extend(files::walk_dirs(&media_path, self.settings.rename.max_depth)?
+                .into_iter()
+                .filter(|d| media_path != d.path())
+                .map(|d| DiskPath::new(d, base_info.name(), &self.release_date_regex))
+                .filter(|d| exclude_unsimilar(d, self.settings.rename.similarity_percent, base_info.name())));
+        }
 
         name_variants.sort_by(|a, b| a.similarity.cmp(&b.similarity));
 
+        // the same media can live in more than one library root
+        let mut seen = HashSet::new();
         let name_variants: Vec<String> = name_variants.into_iter()
             .map(|d| d.file_name)
+            .filter(|n| seen.insert(n.clone()))
             .collect();
 
         if name_variants.is_empty() {
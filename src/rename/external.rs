@@ -9,16 +9,25 @@ use tracing::warn;
 
 use crate::{db::DbClient, config::Settings};
 
-use super::{Renamer, name::BaseInfo, RenamedMediaOptions, MediaFileType, MediaDescription, cache::OnlineCacheItem, MediaRenameOrigin};
+use super::{Renamer, name::BaseInfo, RenamedMediaOptions, MediaFileType, MediaDescription, MediaActor, cache::OnlineCacheItem, MediaRenameOrigin};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ExternalMedia {
     pub title: String,
     pub poster_path: Option<String>,
+    pub backdrop_path: Option<String>,
     pub date: String,
     pub description: String,
     pub id: i32,
     pub cast: Vec<String>,
+    pub actors: Vec<MediaActor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExternalEpisode {
+    pub season: u32,
+    pub episode: u32,
+    pub air_date: Option<String>,
 }
 
 #[async_trait]
@@ -27,6 +36,11 @@ pub trait ExternalSearcher: Send + Sync {
     async fn search_movie(&self, query: &str, year: Option<i32>) -> eyre::Result<Vec<ExternalMedia>>;
 }
 
+#[async_trait]
+pub trait ExternalEpisodeSearcher: Send + Sync {
+    async fn tv_episodes(&self, id: i32) -> eyre::Result<Vec<ExternalEpisode>>;
+}
+
 pub struct ExternalRenamer<S: ExternalSearcher> {
     settings: Arc<Settings>,
     searcher: S,
@@ -40,10 +54,10 @@ impl <S: ExternalSearcher> ExternalRenamer<S> {
         ExternalRenamer { settings, searcher, db_client, special_chars_regex }
     }
 
-    fn parse_poster(&self, poster_path: String) -> String {
-        match poster_path.is_empty() {
-            true => poster_path,
-            false => format!("{}{}", &self.settings.online.poster_base, &poster_path),
+    fn parse_image(&self, image_base: &str, image_path: String) -> String {
+        match image_path.is_empty() {
+            true => image_path,
+            false => format!("{}{}", image_base, &image_path),
         }
     }
 
@@ -55,11 +69,13 @@ impl <S: ExternalSearcher> ExternalRenamer<S> {
     fn convert_media(&self, media: Vec<ExternalMedia>) -> Vec<MediaDescription> {
         media.into_iter()
             .map(|m| MediaDescription {
-                poster_url: self.parse_poster(m.poster_path.unwrap_or_default()),
+                poster_url: self.parse_image(&self.settings.online.poster_base, m.poster_path.unwrap_or_default()),
+                fanart_url: self.parse_image(&self.settings.online.fanart_base, m.backdrop_path.unwrap_or_default()),
                 title: self.parse_title(m.title),
                 date: m.date,
                 description: m.description,
                 cast: m.cast,
+                actors: m.actors,
             })
             .collect()
     }
@@ -69,10 +85,12 @@ impl <S: ExternalSearcher> ExternalRenamer<S> {
             search_name: base_info.name().to_owned(),
             search_year: base_info.year(),
             cover_path: media_desc.poster_url.clone(),
+            fanart_path: media_desc.fanart_url.clone(),
             title: media_desc.title.clone(),
             date: self.parse_date(&media_desc.date),
             description: media_desc.description.clone(),
             cast: media_desc.cast.clone(),
+            actors: media_desc.actors.clone(),
             media_type,
         }
     }
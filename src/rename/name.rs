@@ -42,6 +42,7 @@ pub struct NameGenerator {
     special_chars_regex: Regex,
     space_merge_regex: Regex,
     year_regex: Regex,
+    comparable_regex: Regex,
     name_trim_regexes: Vec<Regex>,
 }
 
@@ -57,6 +58,7 @@ impl NameGenerator {
             special_chars_regex: Regex::new(r"[^a-zA-Z0-9-\s]").unwrap(),
             space_merge_regex: Regex::new(r"\s{2,}").unwrap(),
             year_regex: Regex::new(r"\s\d{4}$").unwrap(),
+            comparable_regex: Regex::new(r"[^a-z0-9]+").unwrap(),
             name_trim_regexes,
         }
     }
@@ -88,6 +90,10 @@ impl NameGenerator {
         }
     }
 
+    pub fn comparable_name(&self, name: &str) -> String {
+        self.comparable_regex.replace_all(&name.to_lowercase().replace('&', "and"), " ").trim().to_owned()
+    }
+
     pub fn generate_media_descriptions(&self, titles: Vec<String>) -> Vec<MediaDescription> {
         titles.into_iter()
             .map(|t| {
@@ -97,10 +103,12 @@ impl NameGenerator {
                 };
                 MediaDescription {
                     poster_url: String::new(),
+                    fanart_url: String::new(),
                     title,
                     date,
                     description: String::new(),
                     cast: vec![],
+                    actors: vec![],
                 }
             })
             .collect()
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use eyre::eyre;
+use tracing::{info, warn};
+
+use crate::{
+    config::{LibraryRoot, Settings},
+    files,
+    quality::QualityParser,
+    rename::MediaFileType,
+    search::MediaFileGroup,
+};
+
+const DEFAULT_DOWNLOADS_ROOT: &str = "downloads";
+const DEFAULT_MOVIES_ROOT: &str = "movies";
+const DEFAULT_TV_ROOT: &str = "tv";
+
+pub fn downloads_roots(settings: &Settings) -> Vec<(String, PathBuf)> {
+    let mut roots = vec![(DEFAULT_DOWNLOADS_ROOT.to_owned(), PathBuf::from(&settings.filesystem.downloads_path))];
+    roots.extend(settings.filesystem.downloads_roots.iter().map(|r| (r.name.clone(), PathBuf::from(&r.path))));
+    roots
+}
+
+// the deepest root wins, in case one downloads root is nested in another
+pub fn downloads_root_of(settings: &Settings, path: &Path) -> Option<PathBuf> {
+    let path = files::canonicalize(path);
+    downloads_roots(settings).into_iter()
+        .map(|(_, root)| files::canonicalize(&root))
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+}
+
+pub fn is_downloads_root(settings: &Settings, path: &Path) -> bool {
+    let path = files::canonicalize(path);
+    downloads_roots(settings).iter().any(|(_, root)| files::canonicalize(root) == path)
+}
+
+// configured roots come first so their rules get a chance before the default library roots
+pub fn library_roots(settings: &Settings, media_type: MediaFileType) -> Vec<LibraryRoot> {
+    let defaults = [
+        (DEFAULT_MOVIES_ROOT, &settings.filesystem.movies_path, MediaFileType::MOVIE),
+        (DEFAULT_TV_ROOT, &settings.filesystem.tv_path, MediaFileType::TV),
+    ];
+
+    settings.filesystem.library_roots.iter()
+        .cloned()
+        .chain(defaults.into_iter().map(|(name, path, media_type)| LibraryRoot {
+            name: name.to_owned(),
+            path: path.clone(),
+            media_type,
+            resolutions: vec![],
+            genres: vec![],
+            name_range: None,
+        }))
+        .filter(|r| media_type == MediaFileType::UNKNOWN || r.media_type == media_type)
+        .collect()
+}
+
+pub fn is_root(settings: &Settings, path: &Path) -> bool {
+    let path = files::canonicalize(path);
+    is_downloads_root(settings, &path) || library_roots(settings, MediaFileType::UNKNOWN).iter()
+        .any(|r| files::canonicalize(Path::new(&r.path)) == path)
+}
+
+pub fn choose_library_root(settings: &Settings, media_type: MediaFileType, file_group: &MediaFileGroup, genres: &[String]) -> eyre::Result<PathBuf> {
+    let roots = library_roots(settings, media_type);
+
+    if let Some(existing) = roots.iter().find(|r| Path::new(&r.path).join(&file_group.name).is_dir()) {
+        info!("media {} already exists in library root {}", &file_group.name, &existing.name);
+        return Ok(PathBuf::from(&existing.path));
+    }
+
+    let resolution = best_resolution(file_group);
+    let matching: Vec<&LibraryRoot> = roots.iter()
+        .filter(|r| accepts(r, &file_group.name, resolution.as_deref(), genres))
+        .collect();
+
+    // ties on free space keep the configured order
+    let chosen = match settings.filesystem.prefer_free_space {
+        true => matching.into_iter().rev().max_by_key(|r| files::free_space(Path::new(&r.path)).unwrap_or_else(|e| {
+            warn!("could not read free space of library root {}: {:?}", &r.name, e);
+            0
+        })),
+        false => matching.into_iter().next(),
+    };
+
+    match chosen {
+        Some(root) => {
+            info!("chose library root {} for media {}", &root.name, &file_group.name);
+            Ok(PathBuf::from(&root.path))
+        },
+        None => Err(eyre!("no {:?} library root accepts media {}", media_type, &file_group.name)),
+    }
+}
+
+fn best_resolution(file_group: &MediaFileGroup) -> Option<String> {
+    let parser = QualityParser::new();
+    file_group.videos.iter()
+        .map(|v| parser.parse(&Path::new(&file_group.name).join(v).to_string_lossy()))
+        .max_by(|a, b| a.compare(b))
+        .and_then(|q| q.resolution)
+}
+
+fn accepts(root: &LibraryRoot, name: &str, resolution: Option<&str>, genres: &[String]) -> bool {
+    let resolution_matches = root.resolutions.is_empty()
+        || resolution.map(|res| root.resolutions.iter().any(|r| r.eq_ignore_ascii_case(res))).unwrap_or(false);
+    let genre_matches = root.genres.is_empty()
+        || genres.iter().any(|g| root.genres.iter().any(|r| r.eq_ignore_ascii_case(g)));
+    let name_matches = match &root.name_range {
+        Some(range) => in_name_range(range, name),
+        None => true,
+    };
+
+    resolution_matches && genre_matches && name_matches
+}
+
+fn in_name_range(range: &str, name: &str) -> bool {
+    let bounds = range.split_once('-')
+        .and_then(|(start, end)| Some((start.trim().chars().next()?, end.trim().chars().next()?)));
+    let first = name.chars().find(|c| c.is_alphanumeric());
+
+    match (bounds, first) {
+        (Some((start, end)), Some(first)) => {
+            let first = first.to_ascii_uppercase();
+            start.to_ascii_uppercase() <= first && first <= end.to_ascii_uppercase()
+        },
+        (None, _) => {
+            warn!("invalid library root name range {}, expected something like A-M", range);
+            false
+        },
+        (_, None) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::in_name_range;
+
+    #[test]
+    fn name_matched_to_range() {
+        assert!(in_name_range("A-M", "Heat"));
+        assert!(in_name_range("a-m", "...alien"));
+        assert!(!in_name_range("A-M", "Nope"));
+        assert!(in_name_range("N-Z", "Zodiac"));
+        assert!(in_name_range("0-9", "2012"));
+        assert!(!in_name_range("AM", "Alien"));
+    }
+}
@@ -1,82 +1,223 @@
 use std::{
     collections::HashMap,
+    ffi::OsStr,
+    fs,
     path::{PathBuf, MAIN_SEPARATOR_STR},
     sync::Arc,
+    time::Duration,
 };
 
-use axum::{routing::get, Extension, Json, Router};
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::Query,
+    http::header,
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use eyre::Context;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 use utoipa::ToSchema;
 use walkdir::DirEntry;
 
 use crate::{
+    archive::ArchiveDetector,
+    classify::{MediaClassification, MediaClassifier},
     config::Settings,
-    files,
+    episode::EpisodeParser,
+    files::{self, PathMatcher},
     http::{self, ApiContext},
+    quality::{MediaQuality, QualityParser},
+    rename::MediaFileType,
+    roots,
 };
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, ToSchema)]
 pub struct MediaFileGroup {
     pub path: String,
     pub name: String,
     pub videos: Vec<String>,
+    #[serde(default, rename(serialize = "videoDetails", deserialize = "videoDetails"))]
+    #[schema(rename = "videoDetails")]
+    pub video_details: Vec<MediaVideo>,
+    #[serde(default, rename(serialize = "totalSize", deserialize = "totalSize"))]
+    #[schema(rename = "totalSize")]
+    pub total_size: u64,
+    #[serde(default, rename(serialize = "suggestedType", deserialize = "suggestedType"))]
+    #[schema(rename = "suggestedType")]
+    pub suggested_type: Option<MediaFileType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<ExcludedVideo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct MediaVideo {
+    pub video: String,
+    pub size: u64,
+    pub modified: i64,
+    #[serde(rename(serialize = "mimeType", deserialize = "mimeType"))]
+    #[schema(rename = "mimeType")]
+    pub mime_type: String,
+    pub quality: MediaQuality,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct ExcludedVideo {
+    pub video: String,
+    pub reason: ExclusionReason,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+pub enum ExclusionReason {
+    PATH,
+    INCOMPLETE,
+    SAMPLE,
+    RECENT,
+    SIZE,
+    CONTENT,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MediaSearchParams {
+    pub debug: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+pub struct MediaArchiveGroup {
+    pub path: String,
+    pub name: String,
+    pub archives: Vec<String>,
 }
 
+type ExclusionRule = fn(&MediaFilesParser, &DirEntry) -> bool;
+
 pub struct MediaFilesParser {
     settings: Arc<Settings>,
     downloads_path: PathBuf,
+    exclude_matcher: PathMatcher,
+    sample_regex: Regex,
+    quality_parser: QualityParser,
+    episode_parser: EpisodeParser,
+    classifier: MediaClassifier,
+    archive_detector: ArchiveDetector,
 }
 
-// TODO: make it async somehow?
 impl MediaFilesParser {
     fn new(settings: Arc<Settings>, downloads_path: PathBuf) -> eyre::Result<Self> {
         Ok(MediaFilesParser {
+            exclude_matcher: PathMatcher::new(&settings.search.exclude_paths, true),
+            sample_regex: Regex::new(&settings.search.sample_regex)
+                .wrap_err_with(|| format!("invalid search sample regex {}", &settings.search.sample_regex))?,
+            quality_parser: QualityParser::new(),
+            episode_parser: EpisodeParser::new(),
+            classifier: MediaClassifier::new(settings.clone()),
+            archive_detector: ArchiveDetector::new(),
             settings,
             downloads_path,
         })
     }
 
-    fn parse(&self, files: Vec<DirEntry>) -> Vec<DirEntry> {
+    // root level files are searched together so videos sharing a name still group, each folder is searched on its own
+    fn search_roots(&self) -> eyre::Result<Vec<(PathBuf, u8)>> {
+        if !self.downloads_path.is_dir() {
+            warn!("downloads path {} is not a dir, nothing to search", self.downloads_path.to_string_lossy());
+            return Ok(vec![]);
+        }
+
+        let mut roots = vec![(self.downloads_path.clone(), 1)];
+        if self.settings.search.max_depth > 1 {
+            for entry in fs::read_dir(&self.downloads_path)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    roots.push((path, self.settings.search.max_depth - 1));
+                }
+            }
+        }
+
+        roots.sort();
+        Ok(roots)
+    }
+
+    fn parse(&self, files: Vec<DirEntry>) -> Vec<(DirEntry, Result<String, ExclusionReason>)> {
         files
             .into_iter()
-            .filter(|p| self.exclude_paths(p))
-            .filter(|p| self.exclude_by_size(p))
-            .filter(|p| self.exclude_by_content(p))
+            .map(|p| {
+                let mime = match self.exclusion_reason(&p) {
+                    Some(reason) => Err(reason),
+                    None => self.video_mime(&p).ok_or(ExclusionReason::CONTENT),
+                };
+                (p, mime)
+            })
             .collect()
     }
 
+    fn exclusion_reason(&self, path: &DirEntry) -> Option<ExclusionReason> {
+        let rules: [(ExclusionRule, ExclusionReason); 5] = [
+            (Self::exclude_paths, ExclusionReason::PATH),
+            (Self::exclude_incomplete, ExclusionReason::INCOMPLETE),
+            (Self::exclude_samples, ExclusionReason::SAMPLE),
+            (Self::exclude_recently_modified, ExclusionReason::RECENT),
+            (Self::exclude_by_size, ExclusionReason::SIZE),
+        ];
+
+        rules.iter()
+            .find(|(keep, _)| !keep(self, path))
+            .map(|(_, reason)| *reason)
+    }
+
     fn exclude_paths(&self, path: &DirEntry) -> bool {
-        for exclude_path in &self.settings.search.exclude_paths {
-            let path = path.path();
-            if path.is_absolute() && path.to_string_lossy().contains(exclude_path) {
-                return false;
-            }
+        !self.exclude_matcher.matches(path.path())
+    }
+
+    fn exclude_incomplete(&self, path: &DirEntry) -> bool {
+        let ext = path.path().extension().unwrap_or_default();
+        !self.settings.search.incomplete_ext.iter().any(|e| OsStr::new(e).eq_ignore_ascii_case(ext))
+    }
+
+    fn exclude_samples(&self, path: &DirEntry) -> bool {
+        let (_, _, video) = self.split_group(path.path().to_path_buf());
+        !self.sample_regex.is_match(&video)
+    }
+
+    fn exclude_recently_modified(&self, path: &DirEntry) -> bool {
+        if self.settings.search.quiet_period_secs == 0 {
+            return true;
+        }
+
+        let quiet_period = Duration::from_secs(self.settings.search.quiet_period_secs);
+        match path.metadata().ok().and_then(|data| data.modified().ok()) {
+            // a modified time in the future can't be trusted to be quiet either
+            Some(modified) => modified.elapsed().map(|e| e >= quiet_period).unwrap_or(false),
+            None => false,
         }
-        true
     }
 
-    fn exclude_by_content(&self, path: &DirEntry) -> bool {
+    fn video_mime(&self, path: &DirEntry) -> Option<String> {
         let ftype = match infer::get_from_path(path.path()) {
             Ok(ftype) => ftype,
             Err(e) => {
                 warn!("error occurred when infering file type: {:?}", e);
-                return false  
+                return None
             },
         };
-        
-        if let Some(mime) = ftype {
-            for allowed_mime in &self.settings.search.video_mime_types {
-                if allowed_mime == mime.mime_type() {
-                    return true;
-                }
-            }
-            if mime.mime_type().starts_with("video/") {
-                return true;
+
+        let mime = ftype?.mime_type();
+        for allowed_mime in &self.settings.search.video_mime_types {
+            if allowed_mime == mime {
+                return Some(mime.to_owned());
             }
         }
+        if mime.starts_with("video/") {
+            return Some(mime.to_owned());
+        }
 
-        false
+        None
     }
 
     fn exclude_by_size(&self, path: &DirEntry) -> bool {
@@ -86,57 +227,191 @@ impl MediaFilesParser {
         }
     }
 
-    pub fn generate(&self, files: Vec<DirEntry>) -> Vec<MediaFileGroup> {
+    fn split_group(&self, file: PathBuf) -> (PathBuf, Option<String>, String) {
+        let segments: Vec<String> = file
+            .iter()
+            .skip(self.downloads_path.iter().count())
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+
+        match segments.len() {
+            1 => (self.downloads_path.to_path_buf(), None, segments[0].clone()),
+            _ => (self.downloads_path.join(&segments[0]), Some(segments[0].clone()), segments[1..].join(MAIN_SEPARATOR_STR)),
+        }
+    }
+
+    fn video_details(&self, file: &DirEntry, video: &str, mime_type: String) -> MediaVideo {
+        let metadata = file.metadata().ok();
+        let relative = file.path().strip_prefix(&self.downloads_path).unwrap_or(file.path());
+        let (season, episode) = self.episode_parser.parse(relative);
+
+        MediaVideo {
+            video: video.to_owned(),
+            size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+            modified: metadata.and_then(|m| m.modified().ok())
+                .map(|t| DateTime::<Utc>::from(t).timestamp_millis())
+                .unwrap_or(0),
+            mime_type,
+            quality: self.quality_parser.parse_file(relative),
+            season,
+            episode,
+        }
+    }
+
+    pub fn generate(&self, files: Vec<DirEntry>, debug: bool) -> Vec<MediaFileGroup> {
         let mut tmp_map = HashMap::new();
 
-        for video_path in self.parse(files) {
-            let video_path_segments: Vec<String> = video_path
-                .into_path()
-                .iter()
-                .skip(self.downloads_path.iter().count())
-                .map(|p| p.to_string_lossy().into_owned())
-                .collect();
-
-            let mut name = video_path_segments[0].clone();
-            let mut path = self.downloads_path.to_path_buf();
-            let mut video = name.clone();
-
-            if video_path_segments.len() > 1 {
-                path = self.downloads_path.join(&name);
-                video = String::from(&video_path_segments[1..].join(MAIN_SEPARATOR_STR));
-            } else {
-                name = String::from(&name[..name.rfind('.').unwrap_or(name.len())]);
+        for (video_path, mime) in self.parse(files) {
+            if mime.is_err() && !debug {
+                continue;
             }
 
+            let (path, name, video) = self.split_group(video_path.path().to_path_buf());
+            let name = name.unwrap_or_else(|| String::from(&video[..video.rfind('.').unwrap_or(video.len())]));
+            let path = path.to_string_lossy().into_owned();
+
+            let group = tmp_map
+                .entry((path.clone(), name.clone()))
+                .or_insert_with(|| MediaFileGroup { path, name, ..Default::default() });
+            match mime {
+                Ok(mime) => {
+                    let details = self.video_details(&video_path, &video, mime);
+                    group.total_size += details.size;
+                    group.video_details.push(details);
+                    group.videos.push(video);
+                },
+                Err(reason) => group.excluded.push(ExcludedVideo { video, reason }),
+            }
+        }
+
+        tmp_map
+            .into_values()
+            .map(|mut group| {
+                group.suggested_type = self.suggest_type(&group);
+                group
+            })
+            .collect()
+    }
+
+    fn suggest_type(&self, group: &MediaFileGroup) -> Option<MediaFileType> {
+        match group.videos.is_empty() {
+            true => None,
+            false => Some(MediaClassification::from(self.classifier.name_score(group)).media_type),
+        }
+    }
+
+    pub fn generate_archives(&self, files: Vec<DirEntry>) -> Vec<MediaArchiveGroup> {
+        let mut tmp_map = HashMap::new();
+
+        for file in files.into_iter().filter(|p| self.exclude_paths(p)) {
+            let volume = match self.archive_detector.entry(file.path()) {
+                Some(volume) => volume,
+                None => continue,
+            };
+
+            let (path, name, archive) = self.split_group(file.into_path());
             tmp_map
-                .entry((path.to_string_lossy().into_owned(), name))
+                .entry((path.to_string_lossy().into_owned(), name.unwrap_or(volume.set_name)))
                 .or_insert(vec![])
-                .push(video);
+                .push(archive);
         }
 
         tmp_map
             .into_iter()
-            .map(|((path, name), videos)| MediaFileGroup { path, name, videos })
+            .map(|((path, name), archives)| MediaArchiveGroup { path, name, archives })
             .collect()
     }
 }
 
 pub fn router() -> Router {
-    Router::new().route("/api/v1/media-searches", get(search_media))
+    Router::new()
+        .route("/api/v1/media-searches", get(search_media))
+        .route("/api/v1/media-searches/stream", get(stream_media))
+        .route("/api/v1/media-searches/archives", get(search_archives))
+}
+
+async fn search_groups(settings: Arc<Settings>, debug: bool) -> eyre::Result<impl Stream<Item = eyre::Result<Vec<MediaFileGroup>>>> {
+    let parallelism = settings.search.parallelism.max(1);
+
+    let mut search_roots = vec![];
+    for (_, downloads_path) in roots::downloads_roots(&settings) {
+        let parser = Arc::new(MediaFilesParser::new(settings.clone(), downloads_path)?);
+        let roots_parser = parser.clone();
+        let roots = tokio::task::spawn_blocking(move || roots_parser.search_roots()).await
+            .wrap_err("search roots task could not complete")??;
+        search_roots.extend(roots.into_iter().map(|(root, depth)| (parser.clone(), root, depth)));
+    }
+
+    Ok(stream::iter(search_roots)
+        .map(move |(parser, root, depth)| {
+            tokio::task::spawn_blocking(move || -> eyre::Result<Vec<MediaFileGroup>> {
+                Ok(parser.generate(files::walk_files(&root, depth)?, debug))
+            })
+        })
+        .buffer_unordered(parallelism)
+        .map(|res| res.wrap_err("search task could not complete").and_then(|groups| groups)))
 }
 
 #[utoipa::path(get, path = "/api/v1/media-searches",
+    params(
+        ("debug" = Option<bool>, Query, description = "include excluded files and the reason they were excluded"),
+    ),
     responses(
         (status = 200, description = "Search media files", body = [MediaFileGroup])
     )
 )]
-pub async fn search_media(ctx: Extension<ApiContext>) -> http::Result<Json<Vec<MediaFileGroup>>> {
-    info!("search_media request received");
+pub async fn search_media(ctx: Extension<ApiContext>, Query(params): Query<MediaSearchParams>) -> http::Result<Json<Vec<MediaFileGroup>>> {
+    info!("search_media request received with params {:?}", params);
+
+    let groups: Vec<Vec<MediaFileGroup>> = search_groups(ctx.settings.clone(), params.debug.unwrap_or(false)).await?
+        .try_collect().await?;
+
+    Ok(Json(groups.into_iter().flatten().collect()))
+}
+
+#[utoipa::path(get, path = "/api/v1/media-searches/stream",
+    params(
+        ("debug" = Option<bool>, Query, description = "include excluded files and the reason they were excluded"),
+    ),
+    responses(
+        (status = 200, description = "Stream media files as newline delimited JSON, one group per line as soon as it is found", 
+            body = MediaFileGroup, content_type = "application/x-ndjson")
+    )
+)]
+pub async fn stream_media(ctx: Extension<ApiContext>, Query(params): Query<MediaSearchParams>) -> http::Result<impl IntoResponse> {
+    info!("stream_media request received with params {:?}", params);
+
+    let lines = search_groups(ctx.settings.clone(), params.debug.unwrap_or(false)).await?
+        .map(|groups| {
+            let mut line = vec![];
+            for group in groups? {
+                serde_json::to_writer(&mut line, &group)?;
+                line.push(b'\n');
+            }
+            Ok::<Bytes, eyre::Report>(Bytes::from(line))
+        })
+        .inspect_err(|e| warn!("media search stream stopped: {:?}", e));
+
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], StreamBody::new(lines)))
+}
+
+#[utoipa::path(get, path = "/api/v1/media-searches/archives",
+    responses(
+        (status = 200, description = "Search archived media releases that need extracting", body = [MediaArchiveGroup])
+    )
+)]
+pub async fn search_archives(ctx: Extension<ApiContext>) -> http::Result<Json<Vec<MediaArchiveGroup>>> {
+    info!("search_archives request received");
 
     let settings = ctx.settings.clone();
-    let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
-    let files = files::walk_files(&downloads_path, settings.search.max_depth)?;
-    let parser = MediaFilesParser::new(settings, downloads_path)?;
+    let archives = tokio::task::spawn_blocking(move || -> eyre::Result<Vec<MediaArchiveGroup>> {
+        let mut archives = vec![];
+        for (_, downloads_path) in roots::downloads_roots(&settings) {
+            let files = files::walk_files(&downloads_path, settings.search.max_depth)?;
+            archives.extend(MediaFilesParser::new(settings.clone(), downloads_path)?.generate_archives(files));
+        }
+        Ok(archives)
+    }).await.wrap_err("search archives task could not complete")??;
 
-    Ok(Json(parser.generate(files)))
+    Ok(Json(archives))
 }
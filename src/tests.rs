@@ -12,6 +12,7 @@ use utils::{tests::EmptyDb, config::init_config};
 use crate::{
     config::Settings,
     download::{DownloadCacheRepo, DownloadedMedia},
+    moving::{job::{MoveJob, MoveJobRepo}, journal::{MoveJournalEntry, MoveJournalRepo}},
     rename::{
         name::BaseInfo,
         cache::{OnlineCacheItem, OnlineCacheRepo},
@@ -45,6 +46,44 @@ impl DownloadCacheRepo for EmptyDb {
     }
 }
 
+#[async_trait]
+impl MoveJobRepo for EmptyDb {
+    async fn save_job(&self, _job: &MoveJob) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    async fn find_job(&self, _id: &str) -> eyre::Result<Option<MoveJob>> {
+        Ok(None)
+    }
+
+    async fn find_jobs(&self, _skip: u64, _limit: u64) -> eyre::Result<Vec<MoveJob>> {
+        Ok(vec![])
+    }
+
+    async fn count_jobs(&self) -> eyre::Result<u64> {
+        Ok(0)
+    }
+
+    async fn find_unfinished_jobs(&self) -> eyre::Result<Vec<MoveJob>> {
+        Ok(vec![])
+    }
+}
+
+#[async_trait]
+impl MoveJournalRepo for EmptyDb {
+    async fn save_entry(&self, _entry: &MoveJournalEntry) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    async fn find_entry(&self, _id: &str) -> eyre::Result<Option<MoveJournalEntry>> {
+        Ok(None)
+    }
+
+    async fn find_entries_by_media_path(&self, _media_path: &str) -> eyre::Result<Vec<MoveJournalEntry>> {
+        Ok(vec![])
+    }
+}
+
 fn init_test_logging() {
     let _r = tracing_subscriber::fmt().pretty().try_init();
 }
@@ -60,6 +99,8 @@ pub fn create_test_settings() -> Settings {
     settings.filesystem.downloads_path = base_path.join("downloads").to_string_lossy().into_owned();
     settings.filesystem.movies_path = base_path.join("movies").to_string_lossy().into_owned();
     settings.filesystem.tv_path = base_path.join("tv").to_string_lossy().into_owned();
+    settings.trash.path = base_path.join("trash").to_string_lossy().into_owned();
+    settings.images.path = base_path.join("images").to_string_lossy().into_owned();
 
     fs::create_dir_all(Path::new(&settings.filesystem.downloads_path)).unwrap();
     fs::create_dir_all(Path::new(&settings.filesystem.movies_path)).unwrap();
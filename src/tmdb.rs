@@ -5,10 +5,12 @@ use async_trait::async_trait;
 use eyre::eyre;
 use serde::{Serialize, Deserialize, de::DeserializeOwned};
 
-use crate::{config::Settings, rename::external::{ExternalSearcher, ExternalMedia}};
+use crate::{config::Settings, rename::{MediaActor, external::{ExternalEpisode, ExternalEpisodeSearcher, ExternalSearcher, ExternalMedia}}};
 
 const SEARCH_PATS: &[&str; 4] = &["{base_url}", "{api_key}", "{query}", "{year}"];
 const CREDIT_PATS: &[&str; 3] = &["{base_url}", "{id}", "{api_key}"];
+const DETAILS_PATS: &[&str; 3] = &["{base_url}", "{id}", "{api_key}"];
+const SEASON_PATS: &[&str; 4] = &["{base_url}", "{id}", "{season}", "{api_key}"];
 
 #[derive(Debug, Serialize, Deserialize)]
 struct MovieResults {
@@ -30,22 +32,50 @@ struct TvResults {
 struct Movie {
     title: String,
     poster_path: Option<String>,
+    backdrop_path: Option<String>,
     release_date: String,
     overview: String,
     id: i32,
     #[serde(skip_deserializing)]
     cast: Vec<String>,
+    #[serde(skip_deserializing)]
+    actors: Vec<MediaActor>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Tv {
     name: String,
     poster_path: Option<String>,
+    backdrop_path: Option<String>,
     first_air_date: String,
     overview: String,
     id: i32,
     #[serde(skip_deserializing)]
     cast: Vec<String>,
+    #[serde(skip_deserializing)]
+    actors: Vec<MediaActor>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TvDetails {
+    seasons: Vec<TvSeason>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TvSeason {
+    season_number: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TvSeasonDetails {
+    episodes: Vec<TvEpisode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TvEpisode {
+    season_number: u32,
+    episode_number: u32,
+    air_date: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -55,6 +85,7 @@ struct Credits {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Person {
+    name: String,
     character: String,
 }
 
@@ -88,16 +119,32 @@ impl TmdbAPI {
         Ok(url_builder.replace_all(credits_url, replacements))
     }
 
+    fn produce_details_url(&self, details_url: &str, id: String) -> eyre::Result<String> {
+        let tmdb_cfg = &self.settings.tmdb;
+        let replacements: &[&str; 3] = &[&tmdb_cfg.base_url, &id, &tmdb_cfg.api_key];
+        let url_builder = AhoCorasick::new(DETAILS_PATS)?;
+        Ok(url_builder.replace_all(details_url, replacements))
+    }
+
+    fn produce_season_url(&self, season_url: &str, id: String, season: u32) -> eyre::Result<String> {
+        let tmdb_cfg = &self.settings.tmdb;
+        let replacements: &[&str; 4] = &[&tmdb_cfg.base_url, &id, &season.to_string(), &tmdb_cfg.api_key];
+        let url_builder = AhoCorasick::new(SEASON_PATS)?;
+        Ok(url_builder.replace_all(season_url, replacements))
+    }
+
     fn convert_tv(&self, shows: Vec<Tv>) -> Vec<ExternalMedia> {
         shows.into_iter()
             .map(|s| {
                 ExternalMedia { 
                     title: s.name, 
                     poster_path: s.poster_path, 
+                    backdrop_path: s.backdrop_path, 
                     date: s.first_air_date, 
                     description: s.overview, 
                     id: s.id, 
                     cast: s.cast, 
+                    actors: s.actors, 
                 }
             })
             .collect()
@@ -109,10 +156,12 @@ impl TmdbAPI {
                 ExternalMedia { 
                     title: m.title, 
                     poster_path: m.poster_path, 
+                    backdrop_path: m.backdrop_path, 
                     date: m.release_date, 
                     description: m.overview, 
                     id: m.id, 
                     cast: m.cast, 
+                    actors: m.actors, 
                 }
             })
             .collect()
@@ -140,7 +189,8 @@ impl ExternalSearcher for TmdbAPI {
             let id = tv.id.to_string();
             let url = self.produce_credits_url(&tmdb_cfg.tv_credits_url, id)?;
             let resp = self.client.get(url).send().await?.json::<Credits>().await?;  
-            tv.cast = resp.cast.into_iter().map(|p| p.character).collect();
+            tv.cast = resp.cast.iter().map(|p| p.character.clone()).collect();
+            tv.actors = resp.cast.into_iter().map(|p| MediaActor { name: p.name, role: p.character }).collect();
         }
         
         Ok(self.convert_tv(resp.results))
@@ -155,9 +205,36 @@ impl ExternalSearcher for TmdbAPI {
             let id = movie.id.to_string();
             let url = self.produce_credits_url(&tmdb_cfg.movie_credits_url, id)?;
             let resp = self.client.get(url).send().await?.json::<Credits>().await?;  
-            movie.cast = resp.cast.into_iter().map(|p| p.character).collect();
+            movie.cast = resp.cast.iter().map(|p| p.character.clone()).collect();
+            movie.actors = resp.cast.into_iter().map(|p| MediaActor { name: p.name, role: p.character }).collect();
         }
         
         Ok(self.convert_movies(resp.results))
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl ExternalEpisodeSearcher for TmdbAPI {
+    async fn tv_episodes(&self, id: i32) -> eyre::Result<Vec<ExternalEpisode>> {
+        let tmdb_cfg = &self.settings.tmdb;
+        let url = self.produce_details_url(&tmdb_cfg.tv_details_url, id.to_string())?;
+        let details = self.get_request::<TvDetails>(url).await?;
+
+        let mut episodes = vec![];
+        for season in details.seasons {
+            // season 0 holds the specials, no need to fetch it when they're ignored anyway
+            if self.settings.library.ignore_specials && season.season_number == 0 {
+                continue;
+            }
+            let url = self.produce_season_url(&tmdb_cfg.tv_season_url, id.to_string(), season.season_number)?;
+            let resp = self.get_request::<TvSeasonDetails>(url).await?;
+            episodes.extend(resp.episodes.into_iter().map(|e| ExternalEpisode {
+                season: e.season_number,
+                episode: e.episode_number,
+                air_date: e.air_date.filter(|d| !d.is_empty()),
+            }));
+        }
+
+        Ok(episodes)
+    }
+}
@@ -0,0 +1,225 @@
+use std::{cmp::Reverse, fs, path::{Path, PathBuf}, sync::Arc, time::Duration};
+
+use axum::{extract::Path as ReqPath, routing::{get, post}, Extension, Json, Router};
+use chrono::Utc;
+use eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{config::Settings, error::Error, files, http::{self, ApiContext}};
+
+const ITEM_INFO_FILE: &str = ".trashinfo.json";
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct TrashItem {
+    pub id: String,
+    #[serde(rename(serialize = "originalPath", deserialize = "originalPath"))]
+    #[schema(rename = "originalPath")]
+    pub original_path: String,
+    pub deleted: i64,
+}
+
+impl TrashItem {
+    fn new(original_path: &Path) -> Self {
+        let now = Utc::now();
+        TrashItem {
+            id: format!("{}_{:08x}", now.format("%Y%m%d%H%M%S"), rand::random::<u32>()),
+            original_path: original_path.to_string_lossy().into_owned(),
+            deleted: now.timestamp_millis(),
+        }
+    }
+
+    fn dir(&self, settings: &Settings) -> PathBuf {
+        Path::new(&settings.trash.path).join(&self.id)
+    }
+
+    pub fn is_in_trash(&self, settings: &Settings) -> bool {
+        self.dir(settings).join(ITEM_INFO_FILE).is_file()
+    }
+
+    fn content(&self, settings: &Settings) -> PathBuf {
+        let name = Path::new(&self.original_path).file_name().unwrap_or_default();
+        self.dir(settings).join(name)
+    }
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/v1/trash", get(list_trash).delete(empty_trash))
+        .route("/api/v1/trash/:id/restore", post(restore_trash_item))
+}
+
+#[utoipa::path(get, path = "/api/v1/trash",
+    responses(
+        (status = 200, description = "List items in the trash, newest first", body = [TrashItem])
+    )
+)]
+pub async fn list_trash(ctx: Extension<ApiContext>) -> http::Result<Json<Vec<TrashItem>>> {
+    info!("list_trash request received");
+
+    let settings = ctx.settings.clone();
+    let items = tokio::task::spawn_blocking(move || list_items(&settings)).await
+        .wrap_err("list trash task could not complete")??;
+
+    Ok(Json(items))
+}
+
+#[utoipa::path(post, path = "/api/v1/trash/{id}/restore",
+    params(
+        ("id" = String, Path, description = "id of the trash item to restore"),
+    ),
+    responses(
+        (status = 200, description = "Restore trash item to its original path", body = TrashItem),
+        (status = 404, description = "Trash item not found")
+    )
+)]
+pub async fn restore_trash_item(ctx: Extension<ApiContext>, ReqPath(id): ReqPath<String>) -> http::Result<Json<TrashItem>> {
+    info!("restore_trash_item request received for id {}", id);
+
+    let settings = ctx.settings.clone();
+    let item = tokio::task::spawn_blocking(move || list_items(&settings)).await
+        .wrap_err("list trash task could not complete")??
+        .into_iter()
+        .find(|i| i.id == id);
+
+    let item = match item {
+        Some(item) => item,
+        None => return Err(Error::NotFound),
+    };
+
+    if Path::new(&item.original_path).exists() {
+        return Err(Error::unprocessable_entity([("id", format!("original path is already taken: {}", &item.original_path))]));
+    }
+
+    let settings = ctx.settings.clone();
+    let restored = item.clone();
+    tokio::task::spawn_blocking(move || restore(&settings, &restored)).await
+        .wrap_err("restore trash task could not complete")??;
+
+    Ok(Json(item))
+}
+
+#[utoipa::path(delete, path = "/api/v1/trash",
+    responses(
+        (status = 200, description = "Empty the trash, returning the removed items", body = [TrashItem])
+    )
+)]
+pub async fn empty_trash(ctx: Extension<ApiContext>) -> http::Result<Json<Vec<TrashItem>>> {
+    info!("empty_trash request received");
+
+    let settings = ctx.settings.clone();
+    let items = tokio::task::spawn_blocking(move || empty(&settings)).await
+        .wrap_err("empty trash task could not complete")??;
+
+    Ok(Json(items))
+}
+
+pub fn move_to_trash(settings: &Settings, path: &Path) -> eyre::Result<TrashItem> {
+    move_to_trash_as(settings, path, path)
+}
+
+// the trashed path is restored to original_path, which can differ from where it was trashed from
+pub fn move_to_trash_as(settings: &Settings, path: &Path, original_path: &Path) -> eyre::Result<TrashItem> {
+    let item = TrashItem::new(original_path);
+    let item_dir = item.dir(settings);
+    fs::create_dir_all(&item_dir)
+        .wrap_err_with(|| format!("could not create trash dir {}", item_dir.to_string_lossy()))?;
+
+    let res = write_info(&item_dir, &item)
+        .and_then(|_| files::move_path(path, &item.content(settings), settings.mv.verify_checksum));
+
+    if let Err(e) = res {
+        if let Err(re) = files::delete_dir(&item_dir) {
+            warn!("could not remove unused trash dir {}: {:?}", item_dir.to_string_lossy(), re);
+        }
+        return Err(e);
+    }
+
+    info!("moved path {} to trash as {}", &item.original_path, &item.id);
+    Ok(item)
+}
+
+pub fn list_items(settings: &Settings) -> eyre::Result<Vec<TrashItem>> {
+    let trash_path = Path::new(&settings.trash.path);
+    if !trash_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut items = vec![];
+    for dir in fs::read_dir(trash_path)? {
+        let info_path = dir?.path().join(ITEM_INFO_FILE);
+        match read_info(&info_path) {
+            Ok(item) => items.push(item),
+            Err(e) => warn!("skipping unreadable trash item {}: {:?}", info_path.to_string_lossy(), e),
+        }
+    }
+
+    items.sort_by_key(|i| Reverse(i.deleted));
+    Ok(items)
+}
+
+pub fn restore(settings: &Settings, item: &TrashItem) -> eyre::Result<()> {
+    let original = Path::new(&item.original_path);
+    if original.exists() {
+        return Err(eyre!("original path is already taken: {}", &item.original_path));
+    }
+
+    files::move_path(&item.content(settings), original, settings.mv.verify_checksum)?;
+    files::delete_dir(&item.dir(settings))?;
+
+    info!("restored trash item {} to {}", &item.id, &item.original_path);
+    Ok(())
+}
+
+pub fn empty(settings: &Settings) -> eyre::Result<Vec<TrashItem>> {
+    remove_items(settings, list_items(settings)?)
+}
+
+// purges right away, then on every interval for as long as the server runs
+pub fn spawn_purge_task(settings: Arc<Settings>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let purge_settings = settings.clone();
+            match tokio::task::spawn_blocking(move || purge_expired(&purge_settings)).await {
+                Ok(Ok(purged)) if !purged.is_empty() => info!("purged {} expired trash items", purged.len()),
+                Ok(Ok(_)) => (),
+                Ok(Err(e)) => warn!("could not purge expired trash items: {:?}", e),
+                Err(e) => warn!("purge trash task could not complete: {:?}", e),
+            }
+        }
+    });
+}
+
+pub fn purge_expired(settings: &Settings) -> eyre::Result<Vec<TrashItem>> {
+    let expired_before = Utc::now().timestamp_millis() - settings.trash.retention_days as i64 * DAY_MILLIS;
+    let expired = list_items(settings)?
+        .into_iter()
+        .filter(|i| i.deleted < expired_before)
+        .collect();
+
+    remove_items(settings, expired)
+}
+
+fn remove_items(settings: &Settings, items: Vec<TrashItem>) -> eyre::Result<Vec<TrashItem>> {
+    for item in &items {
+        files::delete_dir(&item.dir(settings))?;
+        info!("removed trash item {} of {}", &item.id, &item.original_path);
+    }
+
+    Ok(items)
+}
+
+fn write_info(item_dir: &Path, item: &TrashItem) -> eyre::Result<()> {
+    let info_path = item_dir.join(ITEM_INFO_FILE);
+    fs::write(&info_path, serde_json::to_vec(item)?)
+        .wrap_err_with(|| format!("could not write trash info {}", info_path.to_string_lossy()))
+}
+
+fn read_info(info_path: &Path) -> eyre::Result<TrashItem> {
+    Ok(serde_json::from_slice(&fs::read(info_path)?)?)
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use std::{fs::{self, File}, io::Write, path::{Path, PathBuf}, sync::Arc};
+
+    use axum::{extract::Query, Extension, Json};
+    use commander::{
+        archive::extract_media_archives,
+        db::DbClient,
+        error::Error,
+        http::ApiContext,
+        search::{search_archives, search_media, MediaArchiveGroup, MediaSearchParams},
+        tests::create_test_settings,
+        trash,
+    };
+    use utils::tests::{EmptyDb, create_file};
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn create_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn zip_archive_extracted_in_place() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        let video = downloads_path.join("video.tmp");
+        create_file(&video, 20);
+        let video_content = fs::read(&video).unwrap();
+        fs::remove_file(&video).unwrap();
+
+        let release = downloads_path.join("Movie.2020");
+        fs::create_dir_all(&release).unwrap();
+        create_zip(&release.join("movie.zip"), &[("Movie.2020.mp4", &video_content), ("../escaped.txt", b"escaped")]);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        assert!(search_media(Extension(ctx.clone()), Query(MediaSearchParams { debug: None })).await.unwrap().is_empty());
+
+        let mut archives = search_archives(Extension(ctx.clone())).await.unwrap();
+        assert_eq!(1, archives.len());
+
+        let result = extract_media_archives(Extension(ctx.clone()), Json(archives.remove(0))).await.unwrap();
+
+        assert_eq!(release.to_string_lossy(), result.path);
+        assert_eq!(vec!["Movie.2020.mp4"], result.extracted);
+        assert_eq!(vec![release.join("movie.zip").to_string_lossy().into_owned()], result.trashed);
+        assert!(!downloads_path.join("escaped.txt").exists());
+        assert_eq!(1, trash::list_items(&ctx.settings).unwrap().len());
+
+        let videos = search_media(Extension(ctx.clone()), Query(MediaSearchParams { debug: None })).await.unwrap();
+        assert_eq!(1, videos.len());
+        assert_eq!("Movie.2020", &videos[0].name);
+        assert_eq!(vec!["Movie.2020.mp4"], videos[0].videos);
+        assert!(search_archives(Extension(ctx)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn root_zip_extracted_into_own_folder() {
+        let mut settings = create_test_settings();
+        settings.archive.trash_after_extract = false;
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        create_zip(&downloads_path.join("Show.S01.zip"), &[("Show.S01E01.mp4", b"e1"), ("Show.S01E02.mp4", b"e2")]);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let group = MediaArchiveGroup {
+            path: downloads_path.to_string_lossy().into_owned(),
+            name: "Show.S01".to_owned(),
+            archives: vec!["Show.S01.zip".to_owned()],
+        };
+        let result = extract_media_archives(Extension(ctx), Json(group)).await.unwrap();
+
+        assert_eq!(downloads_path.join("Show.S01").to_string_lossy(), result.path);
+        assert_eq!(vec!["Show.S01E01.mp4", "Show.S01E02.mp4"], result.extracted);
+        assert!(result.trashed.is_empty());
+        assert!(downloads_path.join("Show.S01.zip").is_file());
+        assert!(downloads_path.join("Show.S01/Show.S01E02.mp4").is_file());
+    }
+
+    #[tokio::test]
+    async fn archive_outside_downloads_rejected() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let movies_path = PathBuf::from(&settings.filesystem.movies_path);
+        create_zip(&movies_path.join("movie.zip"), &[("movie.mp4", b"movie")]);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let group = MediaArchiveGroup {
+            path: movies_path.to_string_lossy().into_owned(),
+            name: "movie".to_owned(),
+            archives: vec!["movie.zip".to_owned(), "../movie.zip".to_owned()],
+        };
+
+        match extract_media_archives(Extension(ctx), Json(group)).await {
+            Err(Error::UnprocessableEntity { errors }) => {
+                assert!(errors.contains_key("path"));
+                assert_eq!(1, errors["archives"].len());
+            },
+            _ => panic!("expected unprocessable entity"),
+        }
+        assert!(!movies_path.join("movie.mp4").exists());
+    }
+    #[tokio::test]
+    async fn zip_not_extracted_over_existing_files() {
+        let mut settings = create_test_settings();
+        settings.archive.trash_after_extract = false;
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let release = PathBuf::from(&settings.filesystem.downloads_path).join("Existing.Movie.2020");
+        fs::create_dir_all(&release).unwrap();
+        fs::write(release.join("existing.mp4"), b"existing").unwrap();
+        create_zip(&release.join("movie.zip"), &[("new.mp4", b"new"), ("existing.mp4", b"archived")]);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let group = MediaArchiveGroup {
+            path: release.to_string_lossy().into_owned(),
+            name: "Existing.Movie.2020".to_owned(),
+            archives: vec!["movie.zip".to_owned()],
+        };
+
+        assert!(extract_media_archives(Extension(ctx), Json(group)).await.is_err());
+        assert_eq!(b"existing".to_vec(), fs::read(release.join("existing.mp4")).unwrap());
+        assert!(!release.join("new.mp4").exists());
+        assert!(release.join("movie.zip").is_file());
+    }
+    #[tokio::test]
+    async fn spanned_zip_rejected() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let release = PathBuf::from(&settings.filesystem.downloads_path).join("Spanned.Movie.2020");
+        fs::create_dir_all(&release).unwrap();
+        create_zip(&release.join("movie.zip"), &[("movie.mp4", b"movie")]);
+        create_file(release.join("movie.z01"), 4);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        assert!(search_archives(Extension(ctx.clone())).await.unwrap().is_empty());
+
+        let group = MediaArchiveGroup {
+            path: release.to_string_lossy().into_owned(),
+            name: "Spanned.Movie.2020".to_owned(),
+            archives: vec!["movie.zip".to_owned()],
+        };
+        match extract_media_archives(Extension(ctx), Json(group)).await {
+            Err(Error::UnprocessableEntity { errors }) => assert!(errors["archives"][0].contains("spanned zip")),
+            _ => panic!("expected unprocessable entity"),
+        }
+        assert!(!release.join("movie.mp4").exists());
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, time::{Duration, SystemTime}};
+
+    use commander::{files::{copy_dir_verified, copy_verified, PathMatcher}, tests::create_test_settings};
+    use utils::tests::create_file;
+
+    #[test]
+    fn copy_keeps_source_metadata() {
+        let settings = create_test_settings();
+
+        let src = PathBuf::from(&settings.filesystem.downloads_path).join("copied.mp4");
+        let dest = PathBuf::from(&settings.filesystem.movies_path).join("copied.mp4");
+        create_file(&src, 6);
+
+        let modified = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&src).unwrap().set_modified(modified).unwrap();
+
+        let mut copied = 0;
+        copy_verified(&src, &dest, true, &mut |c| copied = c).unwrap();
+
+        let src_meta = fs::metadata(&src).unwrap();
+        let dest_meta = fs::metadata(&dest).unwrap();
+        assert_eq!(src_meta.len(), dest_meta.len());
+        assert_eq!(src_meta.len(), copied);
+        assert_eq!(src_meta.permissions(), dest_meta.permissions());
+        assert_eq!(modified, dest_meta.modified().unwrap());
+        assert_eq!(fs::read(&src).unwrap(), fs::read(&dest).unwrap());
+    }
+
+    #[test]
+    fn failed_copy_leaves_nothing_at_dest() {
+        let settings = create_test_settings();
+
+        let src = PathBuf::from(&settings.filesystem.downloads_path).join("missing.mp4");
+        let dest = PathBuf::from(&settings.filesystem.movies_path).join("missing.mp4");
+
+        assert!(copy_verified(&src, &dest, false, &mut |_| ()).is_err());
+        assert_eq!(0, fs::read_dir(&settings.filesystem.movies_path).unwrap().count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_copy_keeps_links_and_empty_dirs() {
+        let settings = create_test_settings();
+
+        let outside = PathBuf::from(&settings.filesystem.downloads_path).join("outside");
+        let src = PathBuf::from(&settings.filesystem.downloads_path).join("copied dir");
+        let dest = PathBuf::from(&settings.filesystem.movies_path).join("copied dir");
+        create_file(outside.join("big.mkv"), 6);
+        create_file(src.join("movie.mp4"), 6);
+        fs::create_dir_all(src.join("Extras")).unwrap();
+        std::os::unix::fs::symlink(&outside, src.join("linked")).unwrap();
+
+        copy_dir_verified(&src, &dest, true).unwrap();
+
+        assert_eq!(fs::read(src.join("movie.mp4")).unwrap(), fs::read(dest.join("movie.mp4")).unwrap());
+        assert!(dest.join("Extras").is_dir());
+        assert!(fs::symlink_metadata(dest.join("linked")).unwrap().file_type().is_symlink());
+        assert_eq!(outside, fs::read_link(dest.join("linked")).unwrap());
+    }
+
+    #[test]
+    fn path_matcher_handles_globs_and_parent_dirs() {
+        let settings = create_test_settings();
+        let downloads = PathBuf::from(&settings.filesystem.downloads_path);
+        fs::create_dir_all(downloads.join("private").join("movie")).unwrap();
+
+        let patterns = vec![
+            downloads.join("private").to_string_lossy().into_owned(),
+            "nested/*/samples".to_owned(),
+        ];
+        let exact = PathMatcher::new(&patterns, false);
+        let within = PathMatcher::new(&patterns, true);
+
+        assert!(exact.matches(&downloads.join("private")));
+        assert!(exact.matches(&downloads.join("other").join("..").join("private")));
+        assert!(!exact.matches(&downloads.join("private").join("movie")));
+        assert!(within.matches(&downloads.join("private").join("movie")));
+        assert!(exact.matches(&downloads.join("nested").join("show").join("samples")));
+        assert!(!exact.matches(&downloads.join("nested").join("samples")));
+        assert!(!within.matches(&downloads.join("privateer")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_matcher_resolves_symlinks() {
+        let settings = create_test_settings();
+        let downloads = PathBuf::from(&settings.filesystem.downloads_path);
+        let real = downloads.join("real");
+        let link = downloads.join("link");
+        fs::create_dir_all(&real).unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let matcher = PathMatcher::new(&[real.to_string_lossy().into_owned()], true);
+        assert!(matcher.matches(&link));
+        assert!(matcher.matches(&link.join("movie.mp4")));
+
+        let matcher = PathMatcher::new(&[link.to_string_lossy().into_owned()], false);
+        assert!(matcher.matches(&real));
+    }
+}
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, path::Path, sync::{atomic::{AtomicUsize, Ordering}, Arc}};
+
+    use axum::{extract::{Path as ReqPath, State}, http::StatusCode, routing::get, Router};
+    use commander::{
+        config::Settings,
+        images::{encode_key, file_name, original_url, proxy_url, ImageCache},
+        tests::create_test_settings,
+    };
+
+    const PNG_HEADER: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    const IMAGE_SIZE: usize = 400;
+
+    async fn serve_provider_images(hits: Arc<AtomicUsize>) -> SocketAddr {
+        async fn image(State(hits): State<Arc<AtomicUsize>>, ReqPath(name): ReqPath<String>) -> Result<Vec<u8>, StatusCode> {
+            if name == "missing.png" {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            hits.fetch_add(1, Ordering::SeqCst);
+            let mut bytes = PNG_HEADER.to_vec();
+            bytes.resize(IMAGE_SIZE, 0);
+            Ok(bytes)
+        }
+
+        let app = Router::new()
+            .route("/t/p/w92/:name", get(image))
+            .with_state(hits);
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    async fn create_settings(hits: Arc<AtomicUsize>) -> Settings {
+        let addr = serve_provider_images(hits).await;
+        let mut settings = create_test_settings();
+        settings.online.poster_base = format!("http://{}/t/p/w92", addr);
+        settings
+    }
+
+    #[tokio::test]
+    async fn images_fetched_once_then_served_from_cache() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let settings = Arc::new(create_settings(hits.clone()).await);
+
+        let url = format!("{}/poster.png", &settings.online.poster_base);
+        let key = encode_key(&url);
+        let proxied = proxy_url(&settings, &url);
+        assert_eq!(format!("/api/v1/images/{}", key), proxied);
+        assert_eq!(url, original_url(&proxied));
+        assert_eq!("http://elsewhere/poster.png", proxy_url(&settings, "http://elsewhere/poster.png"));
+
+        let cache = ImageCache::new(settings.clone());
+        let first = cache.get(&key).await.unwrap().unwrap();
+        let second = cache.get(&key).await.unwrap().unwrap();
+
+        assert_eq!(1, hits.load(Ordering::SeqCst));
+        assert_eq!("image/png", first.content_type);
+        assert_eq!(IMAGE_SIZE, first.bytes.len());
+        assert_eq!(first.bytes, second.bytes);
+        assert!(Path::new(&settings.images.path).join(file_name(&url)).is_file());
+
+        let reloaded = ImageCache::new(settings);
+        reloaded.get(&key).await.unwrap().unwrap();
+        assert_eq!(1, hits.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_images_evicted() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let mut settings = create_settings(hits.clone()).await;
+        settings.images.max_bytes = (IMAGE_SIZE * 2) as u64;
+        let settings = Arc::new(settings);
+
+        let url = |name: &str| format!("{}/{}", &settings.online.poster_base, name);
+        let key = |name: &str| encode_key(&url(name));
+        let cache = ImageCache::new(settings.clone());
+        cache.get(&key("a.png")).await.unwrap().unwrap();
+        cache.get(&key("b.png")).await.unwrap().unwrap();
+        cache.get(&key("a.png")).await.unwrap().unwrap();
+        cache.get(&key("c.png")).await.unwrap().unwrap();
+
+        let images_path = Path::new(&settings.images.path);
+        assert_eq!(3, hits.load(Ordering::SeqCst));
+        assert!(images_path.join(file_name(&url("a.png"))).is_file());
+        assert!(!images_path.join(file_name(&url("b.png"))).exists());
+        assert!(images_path.join(file_name(&url("c.png"))).is_file());
+
+        cache.get(&key("b.png")).await.unwrap().unwrap();
+        assert_eq!(4, hits.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn long_urls_and_key_case_share_one_cached_file() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let settings = Arc::new(create_settings(hits.clone()).await);
+        let cache = ImageCache::new(settings.clone());
+
+        let url = format!("{}/{}.png", &settings.online.poster_base, "a".repeat(300));
+        let key = encode_key(&url);
+        cache.get(&key).await.unwrap().unwrap();
+        cache.get(&key.to_uppercase()).await.unwrap().unwrap();
+
+        assert_eq!(1, hits.load(Ordering::SeqCst));
+        let files: Vec<String> = std::fs::read_dir(&settings.images.path).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(vec![file_name(&url)], files);
+    }
+
+    #[tokio::test]
+    async fn oversized_images_not_cached() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let mut settings = create_settings(hits.clone()).await;
+        settings.images.max_image_bytes = (IMAGE_SIZE - 1) as u64;
+        let settings = Arc::new(settings);
+        let cache = ImageCache::new(settings.clone());
+
+        assert!(cache.get(&encode_key(&format!("{}/poster.png", &settings.online.poster_base))).await.is_err());
+        assert_eq!(1, hits.load(Ordering::SeqCst));
+        assert!(!Path::new(&settings.images.path).exists() || std::fs::read_dir(&settings.images.path).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn unknown_images_not_found() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let settings = Arc::new(create_settings(hits.clone()).await);
+        let cache = ImageCache::new(settings.clone());
+
+        assert!(cache.get(&encode_key(&format!("{}/missing.png", &settings.online.poster_base))).await.unwrap().is_none());
+        assert!(cache.get(&encode_key("http://elsewhere/poster.png")).await.unwrap().is_none());
+        assert!(cache.get("../secret").await.unwrap().is_none());
+        assert!(cache.get("abc").await.unwrap().is_none());
+        assert_eq!(0, hits.load(Ordering::SeqCst));
+    }
+}
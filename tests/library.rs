@@ -0,0 +1,192 @@
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, sync::Arc};
+
+    use async_trait::async_trait;
+    use axum::{extract::Query, Extension};
+    use commander::{
+        db::DbClient,
+        http::ApiContext,
+        library::{find_missing_episodes, library_duplicates, library_movies, library_tv, LibraryParams, LibrarySeason},
+        rename::{external::{ExternalEpisode, ExternalEpisodeSearcher, ExternalMedia, ExternalSearcher}, MediaFileType},
+        tests::create_test_settings,
+    };
+    use utils::tests::{EmptyDb, create_file};
+
+    struct FakeProvider {
+        shows: Vec<(&'static str, &'static str)>,
+        episodes: Vec<(u32, u32, Option<&'static str>)>,
+    }
+
+    #[async_trait]
+    impl ExternalSearcher for FakeProvider {
+        async fn search_tv(&self, _query: &str, _year: Option<i32>) -> eyre::Result<Vec<ExternalMedia>> {
+            Ok(self.shows.iter().enumerate()
+                .map(|(i, (title, date))| ExternalMedia { title: title.to_string(), poster_path: None, backdrop_path: None, date: date.to_string(), description: String::new(), id: i as i32, cast: vec![], actors: vec![] })
+                .collect())
+        }
+
+        async fn search_movie(&self, _query: &str, _year: Option<i32>) -> eyre::Result<Vec<ExternalMedia>> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait]
+    impl ExternalEpisodeSearcher for FakeProvider {
+        async fn tv_episodes(&self, _id: i32) -> eyre::Result<Vec<ExternalEpisode>> {
+            Ok(self.episodes.iter()
+                .map(|(season, episode, air_date)| ExternalEpisode { season: *season, episode: *episode, air_date: air_date.map(|d| d.to_owned()) })
+                .collect())
+        }
+    }
+
+    fn params(name: Option<&str>, year: Option<i32>, quality: Option<&str>, page: Option<usize>, size: Option<usize>) -> LibraryParams {
+        LibraryParams { name: name.map(|n| n.to_owned()), year, quality: quality.map(|q| q.to_owned()), page, size }
+    }
+
+    #[tokio::test]
+    async fn library_lists_movies() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let movies_path = PathBuf::from(&settings.filesystem.movies_path);
+        create_file(movies_path.join("Heat (1995)/Heat.1995.1080p.BluRay.mp4"), 20);
+        fs::write(movies_path.join("Heat (1995)/Heat.1995.en.srt"), "1\n00:00:01,000 --> 00:00:02,000\nHi\n").unwrap();
+        fs::write(movies_path.join("Heat (1995)/Heat.1995.ro.srt"), "1\n00:00:01,000 --> 00:00:02,000\nSalut\n").unwrap();
+        create_file(movies_path.join("Alien (1979)/Alien.1979.2160p.mp4"), 20);
+        create_file(movies_path.join("Zodiac (2007)/Zodiac.2007.720p.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let all = library_movies(Extension(ctx.clone()), Query(params(None, None, None, None, None))).await.unwrap();
+
+        assert_eq!(3, all.total);
+        let titles: Vec<&str> = all.items.iter().map(|m| m.title.as_str()).collect();
+        assert_eq!(vec!["Alien", "Heat", "Zodiac"], titles);
+        let heat = &all.items[1];
+        assert_eq!(Some(1995), heat.year);
+        assert_eq!("movies", heat.root);
+        assert_eq!(3, heat.files);
+        assert_eq!(Some("1080p".to_owned()), heat.qualities[0].resolution);
+        assert_eq!(vec!["en".to_owned(), "ro".to_owned()], heat.subtitle_languages);
+        assert!(heat.seasons.is_empty());
+
+        let by_name = library_movies(Extension(ctx.clone()), Query(params(Some("zod"), None, None, None, None))).await.unwrap();
+        assert_eq!(1, by_name.total);
+        assert_eq!("Zodiac", by_name.items[0].title);
+
+        let by_year = library_movies(Extension(ctx.clone()), Query(params(None, Some(1979), None, None, None))).await.unwrap();
+        assert_eq!(1, by_year.total);
+        assert_eq!("Alien", by_year.items[0].title);
+
+        let by_quality = library_movies(Extension(ctx.clone()), Query(params(None, None, Some("2160P"), None, None))).await.unwrap();
+        assert_eq!(1, by_quality.total);
+        assert_eq!("Alien", by_quality.items[0].title);
+
+        let second_page = library_movies(Extension(ctx), Query(params(None, None, None, Some(2), Some(2)))).await.unwrap();
+        assert_eq!(3, second_page.total);
+        assert_eq!(2, second_page.page);
+        assert_eq!(1, second_page.items.len());
+        assert_eq!("Zodiac", second_page.items[0].title);
+    }
+
+    #[tokio::test]
+    async fn library_lists_shows_with_episodes() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let tv_path = PathBuf::from(&settings.filesystem.tv_path);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E01.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E02.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 2/Episode 3.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let shows = library_tv(Extension(ctx), Query(params(None, None, None, None, None))).await.unwrap();
+
+        assert_eq!(1, shows.total);
+        let show = &shows.items[0];
+        assert_eq!("Some Show", show.title);
+        assert_eq!(Some(2020), show.year);
+        assert_eq!(3, show.files);
+        assert_eq!(vec![
+            LibrarySeason { season: Some(1), episodes: vec![1, 2] },
+            LibrarySeason { season: Some(2), episodes: vec![3] },
+        ], show.seasons);
+    }
+
+    #[tokio::test]
+    async fn library_reports_duplicates() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let movies_path = PathBuf::from(&settings.filesystem.movies_path);
+        let tv_path = PathBuf::from(&settings.filesystem.tv_path);
+        create_file(movies_path.join("Inception (2010)/Inception.2010.1080p.mp4"), 20);
+        create_file(movies_path.join("Inception (2010-07-15)/Inception.2010.2160p.mp4"), 30);
+        create_file(movies_path.join("Inception (2020)/Inception.2020.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E01.720p.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E01.1080p.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E02.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let duplicates = library_duplicates(Extension(ctx)).await.unwrap();
+
+        assert_eq!(1, duplicates.folders.len());
+        let inception = &duplicates.folders[0];
+        assert_eq!(MediaFileType::MOVIE, inception.media_type);
+        assert_eq!("Inception", inception.title);
+        assert_eq!(Some(2010), inception.year);
+        assert_eq!(2, inception.copies.len());
+        assert!(inception.copies.iter().any(|c| c.qualities[0].resolution == Some("2160p".to_owned())));
+
+        assert_eq!(1, duplicates.episodes.len());
+        let episode = &duplicates.episodes[0];
+        assert_eq!(MediaFileType::TV, episode.media_type);
+        assert_eq!("Some Show", episode.title);
+        assert_eq!((Some(1), Some(1)), (episode.season, episode.episode));
+        assert_eq!(2, episode.copies.len());
+    }
+
+    #[tokio::test]
+    async fn library_reports_missing_episodes() {
+        let settings = create_test_settings();
+
+        let tv_path = PathBuf::from(&settings.filesystem.tv_path);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E01.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 1/Some.Show.S01E03.mp4"), 20);
+        create_file(tv_path.join("Some Show (2020)/Season 2/Some.Show.S02E01.mp4"), 20);
+        create_file(tv_path.join("Other Show (2021)/Other.Show.S01E01.mp4"), 20);
+
+        let provider = FakeProvider {
+            shows: vec![("Another Show", "2021-03-01"), ("Other Show", "1999-01-01"), ("Some Show", "2020-01-01")],
+            episodes: vec![
+                (0, 1, Some("2020-01-01")),
+                (1, 1, Some("2020-01-01")),
+                (1, 2, Some("2020-01-08")),
+                (1, 3, Some("2020-01-15")),
+                (2, 1, Some("2021-01-01")),
+                (2, 2, Some("2021-01-08")),
+                (2, 3, Some("2999-01-01")),
+                (2, 4, None),
+            ],
+        };
+
+        let settings = Arc::new(settings);
+        let report = find_missing_episodes(settings.clone(), &provider, &provider, Some("some".to_owned())).await.unwrap();
+
+        assert_eq!(1, report.len());
+        assert_eq!("Some Show", report[0].title);
+        assert_eq!(Some(2), report[0].provider_id);
+        assert_eq!(None, report[0].error);
+        assert_eq!(vec![
+            LibrarySeason { season: Some(1), episodes: vec![2] },
+            LibrarySeason { season: Some(2), episodes: vec![2] },
+        ], report[0].missing);
+
+        // the provider only knows an Other Show from another year, it is reported unmatched instead of guessed
+        let report = find_missing_episodes(settings, &provider, &provider, None).await.unwrap();
+        let other = report.iter().find(|r| r.title == "Other Show").unwrap();
+        assert_eq!(None, other.provider_id);
+        assert!(other.missing.is_empty());
+        assert!(other.error.is_some());
+    }
+}
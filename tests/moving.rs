@@ -5,16 +5,23 @@ mod tests {
         fs,
         path::{Path, PathBuf},
         sync::Arc,
+        time::Duration,
     };
 
-    use axum::{extract::State, Extension, Json};
+    use axum::{extract::State, routing::get, Extension, Json, Router};
     use commander::{
+        config::{LibraryRoot, NamedRoot},
         db::DbClient,
+        error::Error,
+        files,
         http::ApiContext,
-        moving::{move_media, MediaMoveReq, EPISODE_SEGMENT_REGEX, SUBS_DIR},
-        rename::MediaFileType,
+        moving::{move_media, preview_media_move, submit_move_job, job::MoveJobStatus, journal::{self, MoveJournalEntry}, 
+            plan::{MediaMovePlan, PlannedMove}, conflict::{self, ConflictStrategy}, metadata::{self, MediaMetadata, FANART_FILE, MOVIE_NFO, POSTER_FILE}, release::EXTRAS_DIR, subs::SubsNamer, ImportMode, MediaMoveReq, EPISODE_SEGMENT_REGEX, SUBS_DIR},
+        rename::{MediaActor, MediaDescription, MediaFileType},
+        quality::QualityParser,
         search::MediaFileGroup,
         tests::create_test_settings,
+        trash,
     };
     use regex::Regex;
     use utils::tests::{EmptyDb, create_file};
@@ -34,6 +41,7 @@ mod tests {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
             videos: vec![file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
@@ -42,14 +50,12 @@ mod tests {
             settings: Arc::new(settings),
             db_client,
         };
-        let req = MediaMoveReq {
-            file_group: media,
-            media_type: MediaFileType::MOVIE,
-        };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
         assert_eq!(1, resp.len());
+        assert!(!resp[0].success);
         assert!(path.join(file).is_file());
     }
 
@@ -69,6 +75,7 @@ mod tests {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
             videos: vec![file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
@@ -77,18 +84,112 @@ mod tests {
             settings: Arc::new(settings),
             db_client,
         };
-        let req = MediaMoveReq {
-            file_group: media,
-            media_type: MediaFileType::TV,
-        };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::TV, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(!path.join(file).is_file());
         assert!(p.join(file).is_file());
     }
 
+    #[tokio::test]
+    async fn moving_chooses_library_root_by_rules() {
+        let mut settings = create_test_settings();
+
+        let base_path = PathBuf::from(&settings.filesystem.downloads_path).parent().unwrap().to_path_buf();
+        let seedbox = base_path.join("seedbox");
+        let uhd_root = base_path.join("4k");
+        let late_root = base_path.join("movies n-z");
+        let library_root = |name: &str, path: &Path| LibraryRoot {
+            name: name.to_owned(),
+            path: path.to_string_lossy().into_owned(),
+            media_type: MediaFileType::MOVIE,
+            resolutions: vec![],
+            genres: vec![],
+            name_range: None,
+        };
+        settings.filesystem.downloads_roots = vec![NamedRoot { name: "seedbox".to_owned(), path: seedbox.to_string_lossy().into_owned() }];
+        settings.filesystem.library_roots = vec![
+            LibraryRoot { resolutions: vec!["2160p".to_owned()], ..library_root("4k", &uhd_root) },
+            LibraryRoot { name_range: Some("N-Z".to_owned()), ..library_root("movies n-z", &late_root) },
+        ];
+        let movies_root = PathBuf::from(&settings.filesystem.movies_path);
+
+        let releases = [
+            ("Zodiac.2007.2160p", "Zodiac", uhd_root),
+            ("Nope.2022.1080p", "Nope", late_root),
+            ("Heat.1995.1080p", "Heat", movies_root),
+        ];
+        let mut reqs = vec![];
+        for (release, name, _) in &releases {
+            create_file(seedbox.join(release).join(format!("{}.mp4", release)), 6);
+            reqs.push(MediaMoveReq {
+                file_group: MediaFileGroup {
+                    path: seedbox.join(release).to_string_lossy().into_owned(),
+                    name: name.to_string(),
+                    videos: vec![format!("{}.mp4", release)],
+                    ..Default::default()
+                },
+                media_type: MediaFileType::MOVIE,
+                ..Default::default()
+            });
+        }
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let ctx = ApiContext {
+            settings: Arc::new(settings),
+            db_client,
+        };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(reqs)).await.unwrap();
+
+        assert_eq!(3, resp.len());
+        assert!(resp.iter().all(|r| r.success));
+        for (release, name, root) in &releases {
+            assert!(root.join(name).join(format!("{}.mp4", release)).is_file());
+        }
+    }
+
+    #[tokio::test]
+    async fn moving_unknown_media_classifies_it() {
+        let settings = create_test_settings();
+
+        let name = "Some.Show.S01.1080p";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let files = ["Some.Show.S01E01.mp4", "Some.Show.S01E02.mp4"];
+        for file in files {
+            create_file(path.join(file), 6);
+        }
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: files.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let tv_path = PathBuf::from(&settings.filesystem.tv_path);
+        let ctx = ApiContext {
+            settings: Arc::new(settings),
+            db_client,
+        };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::UNKNOWN, ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
+
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
+        for file in files {
+            assert!(!path.join(file).is_file());
+            assert!(tv_path.join(name).join(file).is_file());
+        }
+    }
+
     #[tokio::test]
     async fn moving_from_downloads_root_doesnt_clean() {
         let settings = create_test_settings();
@@ -106,17 +207,19 @@ mod tests {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
             videos: vec![file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
         let db_client = DbClient::new(Arc::new(EmptyDb));
         let settings = Arc::new(settings);
         let ctx = ApiContext { settings: settings.clone(), db_client, };
-        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(!path.join(file).is_file());
         assert!(Path::new(&settings.filesystem.movies_path) .join(name) .join(file) .is_file());
         assert!(Path::new(&settings.filesystem.downloads_path) .join(random_file) .is_file());
@@ -136,19 +239,25 @@ mod tests {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
             videos: vec![file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
         let db_client = DbClient::new(Arc::new(EmptyDb));
         let settings = Arc::new(settings);
         let ctx = ApiContext { settings: settings.clone(), db_client, };
-        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(!path.is_dir());
         assert!(Path::new(&settings.filesystem.movies_path).join(name).join(file).is_file());
+
+        let trashed = trash::list_items(&settings).unwrap();
+        assert_eq!(1, trashed.len());
+        assert_eq!(path.to_string_lossy(), trashed[0].original_path);
     }
 
     #[tokio::test]
@@ -167,17 +276,19 @@ mod tests {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
             videos: vec![file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
         let db_client = DbClient::new(Arc::new(EmptyDb));
         let settings = Arc::new(settings);
         let ctx = ApiContext { settings: settings.clone(), db_client, };
-        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(path.join(sub).is_file());
     }
 
@@ -190,36 +301,40 @@ mod tests {
         let file = "mivi2.mp4";
         create_file(path.join(file), 6);
 
-        let sub = "sub.srt";
+        let sub = "2_English.srt";
         create_file(path.join(sub), 1,);
 
         let media = MediaFileGroup {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
             videos: vec![file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
         let db_client = DbClient::new(Arc::new(EmptyDb));
         let settings = Arc::new(settings);
         let ctx = ApiContext { settings: settings.clone(), db_client, };
-        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(!path.join(sub).is_file());
-        assert!(Path::new(&settings.filesystem.movies_path).join(name).join(sub).is_file());
+        assert!(Path::new(&settings.filesystem.movies_path).join(name).join("mivi2.en.srt").is_file());
     }
 
     #[tokio::test]
-    async fn tv_subs_moved_to_subs_folder() {
+    async fn unmatched_tv_subs_moved_to_subs_folder() {
         let settings = create_test_settings();
 
         let name = "some show";
         let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
-        let file = "show.mp4";
+        let file = "show.s01e01.mp4";
+        let other_file = "show.s01e02.mp4";
         create_file(path.join(file), 6);
+        create_file(path.join(other_file), 6);
 
         let sub = "showSub.srt";
         create_file(path.join(sub), 1,);
@@ -227,18 +342,20 @@ mod tests {
         let media = MediaFileGroup {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
-            videos: vec![file.to_owned()],
+            videos: vec![file.to_owned(), other_file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
         let db_client = DbClient::new(Arc::new(EmptyDb));
         let settings = Arc::new(settings);
         let ctx = ApiContext { settings: settings.clone(), db_client, };
-        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::TV };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::TV, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(!path.join(sub).is_file());
         assert!(Path::new(&settings.filesystem.tv_path).join(name).join(SUBS_DIR).join(sub).is_file());
     }
@@ -249,30 +366,925 @@ mod tests {
 
         let name = "some show33";
         let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
-        let file = "show.mp4";
+        let file = "show.s02e12.mp4";
+        let other_file = "show.s02e11.mp4";
         create_file(path.join(file), 6);
+        create_file(path.join(other_file), 6);
 
-        let sub = "showSub.srt";
+        let sub = "showSub.eng.SDH.srt";
         let subdir = "show.s02e12.1080p";
         create_file(path.join(subdir).join(sub), 1,);
 
         let media = MediaFileGroup {
             path: path.to_string_lossy().into_owned(),
             name: name.to_owned(),
-            videos: vec![file.to_owned()],
+            videos: vec![other_file.to_owned(), file.to_owned()],
+            ..Default::default()
         };
 
         let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
         let db_client = DbClient::new(Arc::new(EmptyDb));
         let settings = Arc::new(settings);
         let ctx = ApiContext { settings: settings.clone(), db_client, };
-        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::TV };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::TV, ..Default::default() };
 
-        let resp = move_media(State(regex), Extension(ctx), Json(req)).await;
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
 
-        assert_eq!(0, resp.len());
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
         assert!(!path.join(subdir).join(sub).is_file());
-        assert!(Path::new(&settings.filesystem.tv_path).join(name).join(SUBS_DIR)
-            .join(format!("{}.{}", subdir, sub)).is_file());
+        assert!(Path::new(&settings.filesystem.tv_path).join(name).join("show.s02e12.en.sdh.srt").is_file());
+    }
+
+    #[test]
+    fn unknown_move_job_status_not_parsed() {
+        assert_eq!(Ok(MoveJobStatus::FAILED), "FAILED".parse::<MoveJobStatus>());
+        assert_eq!(Ok(MoveJobStatus::RUNNING), "RUNNING".parse::<MoveJobStatus>());
+        assert!("PAUSED".parse::<MoveJobStatus>().is_err());
+    }
+
+    #[test]
+    fn short_sdh_tags_only_trusted_at_the_end() {
+        let namer = SubsNamer::new();
+        let sdh = |name: &str| namer.detect(Path::new(name)).sdh;
+
+        assert!(sdh("Show.S01E01.en.hi.srt"));
+        assert!(sdh("Show.S01E01.hi.eng.srt"));
+        assert!(sdh("Movie.2020.cc.srt"));
+        assert!(sdh("Movie.2020.SDH.1080p.srt"));
+        assert!(!sdh("Hi.Score.Girl.S01E01.srt"));
+        assert!(!sdh("CC.Sakura.S01E01.en.srt"));
+    }
+
+    #[tokio::test]
+    async fn batch_move_reports_each_item() {
+        let settings = create_test_settings();
+
+        let existing = "existing movie";
+        let existing_path = PathBuf::from(&settings.filesystem.downloads_path).join(existing);
+        fs::create_dir_all(PathBuf::from(&settings.filesystem.movies_path).join(existing)).unwrap();
+        create_file(existing_path.join("existing.mp4"), 6);
+
+        let renamed = "renamed movie";
+        let new_path = PathBuf::from(&settings.filesystem.downloads_path).join("new movie");
+        create_file(new_path.join("new.mp4"), 6);
+
+        let existing_req = MediaMoveReq {
+            file_group: MediaFileGroup {
+                path: existing_path.to_string_lossy().into_owned(),
+                name: existing.to_owned(),
+                videos: vec!["existing.mp4".to_owned()],
+                ..Default::default()
+            },
+            media_type: MediaFileType::MOVIE,
+            ..Default::default()
+        };
+        let new_req = MediaMoveReq {
+            file_group: MediaFileGroup {
+                path: new_path.to_string_lossy().into_owned(),
+                name: "new movie".to_owned(),
+                videos: vec!["new.mp4".to_owned()],
+                ..Default::default()
+            },
+            media_type: MediaFileType::MOVIE,
+            name: Some(renamed.to_owned()),
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![existing_req, new_req])).await.unwrap();
+
+        assert_eq!(2, resp.len());
+        assert_eq!(existing_path.to_string_lossy(), resp[0].media_path);
+        assert!(!resp[0].success);
+        assert!(resp[0].error.is_some());
+        assert_eq!(new_path.to_string_lossy(), resp[1].media_path);
+        assert!(resp[1].success);
+        assert!(resp[1].error.is_none());
+        assert!(Path::new(&settings.filesystem.movies_path).join(renamed).join("new.mp4").is_file());
+    }
+
+    #[tokio::test]
+    async fn journal_records_moves_and_deleted_dirs() {
+        let settings = create_test_settings();
+
+        let name = "some journaled movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let file = "journaled.mp4";
+        create_file(path.join(file), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
+
+        assert_eq!(1, resp.len());
+        assert!(resp[0].success);
+        assert!(resp[0].journal_id.is_some());
+    }
+
+    #[tokio::test]
+    async fn moving_writes_nfo_of_chosen_description() {
+        let settings = create_test_settings();
+
+        let name = "Described Movie (2010)";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join("described.movie.2010");
+        let file = "described.mp4";
+        create_file(path.join(file), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+        let description = MediaDescription {
+            poster_url: "http://images/poster.jpg".to_owned(),
+            fanart_url: String::new(),
+            title: "Described Movie".to_owned(),
+            date: "2010-07-15".to_owned(),
+            description: "Heists & <dreams>".to_owned(),
+            cast: vec!["Cobb".to_owned(), String::new()],
+            actors: vec![
+                MediaActor { name: "Leonardo DiCaprio".to_owned(), role: "Cobb".to_owned() },
+                MediaActor { name: "Uncredited Extra".to_owned(), role: String::new() },
+            ],
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, description: Some(description), ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx.clone()), Json(vec![req])).await.unwrap();
+
+        assert!(resp[0].success);
+        let dest = Path::new(&ctx.settings.filesystem.movies_path).join(name);
+        assert!(dest.join(file).is_file());
+        let nfo = fs::read_to_string(dest.join(MOVIE_NFO)).unwrap();
+        assert!(nfo.contains("<movie>"));
+        assert!(nfo.contains("<title>Described Movie</title>"));
+        assert!(nfo.contains("<year>2010</year>"));
+        assert!(nfo.contains("<premiered>2010-07-15</premiered>"));
+        assert!(nfo.contains("<plot>Heists &amp; &lt;dreams&gt;</plot>"));
+        assert!(nfo.contains("<thumb aspect=\"poster\">http://images/poster.jpg</thumb>"));
+        assert!(nfo.contains("<actor>\n    <name>Leonardo DiCaprio</name>\n    <role>Cobb</role>\n  </actor>"));
+        assert!(nfo.contains("<actor>\n    <name>Uncredited Extra</name>\n  </actor>"));
+        assert!(!dest.join(POSTER_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn full_size_artwork_downloaded_within_size_limit() {
+        let mut settings = create_test_settings();
+        settings.mv.write_nfo = false;
+        settings.mv.download_artwork = true;
+        settings.mv.artwork_max_bytes = 100;
+
+        let app = Router::new()
+            .route("/w92/poster.jpg", get(|| async { vec![1u8; 10] }))
+            .route("/original/poster.jpg", get(|| async { vec![1u8; 100] }))
+            .route("/fanart.jpg", get(|| async { vec![1u8; 101] }));
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        settings.online.poster_base = format!("http://{}/w92", addr);
+        settings.online.full_poster_base = format!("http://{}/original", addr);
+
+        let dir = PathBuf::from(&settings.filesystem.movies_path).join("Artwork Movie");
+        fs::create_dir_all(&dir).unwrap();
+        let description = MediaDescription {
+            poster_url: format!("http://{}/w92/poster.jpg", addr),
+            fanart_url: format!("http://{}/fanart.jpg", addr),
+            title: "Artwork Movie".to_owned(),
+            date: String::new(),
+            description: String::new(),
+            cast: vec![],
+            actors: vec![],
+        };
+        let mut journal = MoveJournalEntry::new(dir.to_string_lossy().into_owned());
+        metadata::write(&settings, &MediaMetadata::new(MediaFileType::MOVIE, &dir, description), &mut journal).await;
+
+        assert_eq!(100, fs::read(dir.join(POSTER_FILE)).unwrap().len());
+        assert!(!dir.join(FANART_FILE).exists());
+        assert_eq!(vec![dir.join(POSTER_FILE).to_string_lossy().into_owned()], journal.created_files);
+    }
+
+    #[test]
+    fn undo_removes_generated_files() {
+        let settings = create_test_settings();
+
+        let src_dir = PathBuf::from(&settings.filesystem.downloads_path).join("generated movie");
+        let dest_dir = PathBuf::from(&settings.filesystem.movies_path).join("Generated Movie");
+        let file = "generated.mp4";
+        create_file(dest_dir.join(file), 6);
+        fs::write(dest_dir.join(MOVIE_NFO), "<movie></movie>").unwrap();
+
+        let mut entry = MoveJournalEntry::new(src_dir.to_string_lossy().into_owned());
+        entry.record_move(&PlannedMove::new(&src_dir.join(file), &dest_dir.join(file)));
+        entry.record_created_file(&dest_dir.join(MOVIE_NFO));
+
+        let result = journal::undo(&settings, &mut entry);
+
+        assert_eq!(1, result.restored.len());
+        assert!(result.errors.is_empty());
+        assert!(src_dir.join(file).is_file());
+        assert!(!dest_dir.exists());
+    }
+
+    #[test]
+    fn undo_restores_moved_files() {
+        let settings = create_test_settings();
+
+        let src_dir = PathBuf::from(&settings.filesystem.downloads_path).join("undone movie");
+        let dest_dir = PathBuf::from(&settings.filesystem.movies_path).join("Undone Movie");
+        let file = "undone.mp4";
+        let sub = "undone.srt";
+        create_file(dest_dir.join(file), 6);
+        create_file(dest_dir.join(sub), 1);
+
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::write(src_dir.join("release.nfo"), "release info").unwrap();
+        let trashed = trash::move_to_trash(&settings, &src_dir).unwrap();
+
+        let mut entry = MoveJournalEntry::new(src_dir.to_string_lossy().into_owned());
+        entry.record_move(&PlannedMove::new(&src_dir.join(file), &dest_dir.join(file)));
+        entry.record_move(&PlannedMove::new(&src_dir.join(sub), &dest_dir.join(sub)));
+        entry.record_move(&PlannedMove::new(&src_dir.join("gone.mp4"), &dest_dir.join("gone.mp4")));
+        entry.record_trashed(trashed);
+
+        let result = journal::undo(&settings, &mut entry);
+
+        assert_eq!(2, result.restored.len());
+        assert_eq!(1, result.errors.len());
+        assert!(result.partial);
+        assert_eq!(1, entry.moves.len());
+        assert!(entry.trashed.is_empty());
+        assert!(src_dir.join(file).is_file());
+        assert!(src_dir.join(sub).is_file());
+        assert_eq!("release info", fs::read_to_string(src_dir.join("release.nfo")).unwrap());
+        assert!(!dest_dir.exists());
+        assert!(Path::new(&settings.filesystem.movies_path).is_dir());
+    }
+
+    #[test]
+    fn undo_restores_trashed_release_and_replaced_media() {
+        let settings = create_test_settings();
+
+        let src_dir = PathBuf::from(&settings.filesystem.downloads_path).join("Replacing.Movie.2160p");
+        let dest_dir = PathBuf::from(&settings.filesystem.movies_path).join("Replacing Movie");
+        let (file, sample, nfo, old_file) = ("replacing.mp4", "sample.mp4", "release.nfo", "replacing.720p.mp4");
+        create_file(src_dir.join(file), 6);
+        create_file(src_dir.join(sample), 2);
+        fs::write(src_dir.join(nfo), "release info").unwrap();
+        create_file(dest_dir.join(old_file), 4);
+
+        let plan = MediaMovePlan {
+            videos: vec![PlannedMove::new(&src_dir.join(file), &dest_dir.join(file))],
+            deleted: vec![src_dir.join(sample).to_string_lossy().into_owned()],
+            dirs_to_delete: vec![src_dir.to_string_lossy().into_owned()],
+            replaced: vec![dest_dir.to_string_lossy().into_owned()],
+            ..Default::default()
+        };
+        let mut entry = MoveJournalEntry::new(src_dir.to_string_lossy().into_owned());
+        plan.execute(&settings, &mut entry).unwrap();
+
+        assert!(!src_dir.exists());
+        assert!(dest_dir.join(file).is_file());
+        assert!(!dest_dir.join(old_file).exists());
+        assert_eq!(1, entry.replaced.len());
+        assert_eq!(1, entry.trashed.len());
+
+        let result = journal::undo(&settings, &mut entry);
+
+        assert!(result.errors.is_empty());
+        assert!(src_dir.join(file).is_file());
+        assert!(src_dir.join(sample).is_file());
+        assert_eq!("release info", fs::read_to_string(src_dir.join(nfo)).unwrap());
+        assert!(dest_dir.join(old_file).is_file());
+        assert!(!dest_dir.join(file).exists());
+    }
+
+    #[tokio::test]
+    async fn moving_without_free_space_fails_before_moving() {
+        let mut settings = create_test_settings();
+
+        // only a destination on another filesystem needs free space
+        let shm = Path::new("/dev/shm");
+        if !shm.is_dir() || files::same_filesystem(shm, Path::new(&settings.filesystem.downloads_path)) {
+            return;
+        }
+        let movies_path = shm.join(format!("videosmover-{}", rand::random::<u32>()));
+        settings.filesystem.movies_path = movies_path.to_string_lossy().into_owned();
+        settings.mv.free_space_reserve_bytes = u64::MAX / 2;
+
+        let name = "some big movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let file = "big.mp4";
+        create_file(path.join(file), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
+
+        assert_eq!(1, resp.len());
+        assert!(!resp[0].success);
+        assert!(resp[0].error.as_ref().unwrap().contains("not enough free space"));
+        assert!(path.join(file).is_file());
+        assert!(!movies_path.join(name).exists());
+        let _ = fs::remove_dir_all(&movies_path);
+    }
+
+    #[tokio::test]
+    async fn preview_doesnt_change_disk() {
+        let settings = create_test_settings();
+
+        let name = "some previewed movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let file = "preview.mp4";
+        create_file(path.join(file), 6);
+
+        let sub = "preview.srt";
+        create_file(path.join(sub), 1,);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let plan = preview_media_move(State(regex), Extension(ctx), Json(req)).await.unwrap();
+
+        let movie_dest = Path::new(&settings.filesystem.movies_path).join(name);
+        assert_eq!(1, plan.videos.len());
+        assert_eq!(movie_dest.join(file).to_string_lossy(), plan.videos[0].dest);
+        assert_eq!(1, plan.subs.len());
+        assert_eq!(movie_dest.join(sub).to_string_lossy(), plan.subs[0].dest);
+        assert_eq!(vec![movie_dest.to_string_lossy().into_owned()], plan.dirs_to_create);
+        assert_eq!(vec![path.to_string_lossy().into_owned()], plan.dirs_to_delete);
+        assert!(plan.conflicts.is_empty());
+        assert!(path.join(file).is_file());
+        assert!(path.join(sub).is_file());
+        assert!(!movie_dest.exists());
+    }
+
+    #[tokio::test]
+    async fn existing_movie_skipped_on_request() {
+        let settings = create_test_settings();
+
+        let name = "some skipped movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join("skipped release");
+        let file = "skipped.mp4";
+
+        fs::create_dir_all(PathBuf::from(&settings.filesystem.movies_path).join(name)).unwrap();
+        create_file(path.join(file), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, 
+            on_conflict: ConflictStrategy::SKIP, ..Default::default() };
+
+        let plan = preview_media_move(State(regex), Extension(ctx), Json(req)).await.unwrap();
+
+        assert!(plan.videos.is_empty());
+        assert!(plan.conflicts.is_empty());
+        assert_eq!(1, plan.skipped.len());
+        assert!(path.join(file).is_file());
+    }
+
+    #[tokio::test]
+    async fn existing_movie_kept_with_edition() {
+        let settings = create_test_settings();
+
+        let name = "some duplicated movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join("Duplicated.Movie.1080p.BluRay.x264");
+        let file = "duplicated.mp4";
+
+        let existing = PathBuf::from(&settings.filesystem.movies_path).join(name);
+        create_file(existing.join(file), 6);
+        create_file(path.join(file), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, 
+            on_conflict: ConflictStrategy::KEEP_BOTH, ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
+
+        let edition = Path::new(&settings.filesystem.movies_path).join(format!("{} {{edition-1080p BluRay}}", name));
+        assert!(resp[0].success);
+        assert!(existing.join(file).is_file());
+        assert!(edition.join(file).is_file());
+    }
+
+    #[tokio::test]
+    async fn existing_movie_replaced_if_better() {
+        let settings = create_test_settings();
+
+        let name = "some upgraded movie";
+        let file = "upgraded.mp4";
+
+        let existing = PathBuf::from(&settings.filesystem.movies_path).join(name);
+        let old_file = "upgraded.720p.HDTV.mp4";
+        create_file(existing.join(old_file), 6);
+
+        let worse_path = PathBuf::from(&settings.filesystem.downloads_path).join("Upgraded.Movie.480p.HDTV");
+        create_file(worse_path.join(file), 6);
+        let better_path = PathBuf::from(&settings.filesystem.downloads_path).join("Upgraded.Movie.2160p.WEB-DL");
+        create_file(better_path.join(file), 6);
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+
+        let media = MediaFileGroup {
+            path: worse_path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, 
+            on_conflict: ConflictStrategy::REPLACE_IF_BETTER, ..Default::default() };
+
+        let plan = preview_media_move(State(regex.clone()), Extension(ctx.clone()), Json(req)).await.unwrap();
+
+        assert!(plan.videos.is_empty());
+        assert_eq!(1, plan.skipped.len());
+
+        let media = MediaFileGroup {
+            path: better_path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, 
+            on_conflict: ConflictStrategy::REPLACE_IF_BETTER, ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
+
+        assert!(resp[0].success);
+        assert!(!existing.join(old_file).exists());
+        assert!(existing.join(file).is_file());
+        assert!(worse_path.join(file).is_file());
+    }
+
+    #[test]
+    fn ts_videos_not_ranked_as_cam() {
+        let settings = create_test_settings();
+        let parser = QualityParser::new();
+
+        let existing = PathBuf::from(&settings.filesystem.movies_path).join("Ts Movie (2020)");
+        create_file(existing.join("Movie.2020.1080p.WEB-DL.mkv"), 6);
+        let downloads = PathBuf::from(&settings.filesystem.downloads_path).join("ts movie");
+        let bluray = downloads.join("Movie.2020.1080p.BluRay.ts");
+        let unlabeled = downloads.join("Movie.2020.1080p.ts");
+        create_file(&bluray, 2);
+        create_file(&unlabeled, 2);
+
+        assert!(conflict::is_upgrade(&parser, &[bluray], &existing, 5).unwrap());
+        assert!(!conflict::is_upgrade(&parser, &[unlabeled], &existing, 5).unwrap());
+    }
+
+    #[tokio::test]
+    async fn hardlink_import_keeps_source() {
+        let settings = create_test_settings();
+
+        let name = "some seeding movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let file = "seeding.mp4";
+        let sub = "seeding.srt";
+        create_file(path.join(file), 6);
+        create_file(path.join(sub), 1);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, 
+            import_mode: Some(ImportMode::HARDLINK), ..Default::default() };
+
+        let plan = preview_media_move(State(regex), Extension(ctx), Json(req)).await.unwrap();
+        assert!(plan.dirs_to_delete.is_empty());
+
+        let mut entry = MoveJournalEntry::new(path.to_string_lossy().into_owned());
+        plan.execute(&settings, &mut entry).unwrap();
+
+        let movie_dest = Path::new(&settings.filesystem.movies_path).join(name);
+        assert!(path.join(file).is_file());
+        assert!(path.join(sub).is_file());
+        assert!(movie_dest.join(file).is_file());
+        assert!(movie_dest.join(sub).is_file());
+        assert_eq!(ImportMode::HARDLINK, entry.import_mode);
+
+        let result = journal::undo(&settings, &mut entry);
+
+        assert_eq!(2, result.restored.len());
+        assert!(result.errors.is_empty());
+        assert!(path.join(file).is_file());
+        assert!(!movie_dest.exists());
+    }
+
+    #[tokio::test]
+    async fn restricted_nested_path_not_cleaned() {
+        let settings = create_test_settings();
+
+        let downloads = PathBuf::from(&settings.filesystem.downloads_path);
+        let restricted = downloads.join("some/folder/to/exclude/from/delete/2");
+        let file = "restricted.mp4";
+        create_file(restricted.join(file), 6);
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+
+        for path in [restricted.clone(), downloads.join("other").join("..").join("some/folder/to/exclude/from/delete/2")] {
+            let media = MediaFileGroup {
+                path: path.to_string_lossy().into_owned(),
+                name: "some restricted movie".to_owned(),
+                videos: vec![file.to_owned()],
+                ..Default::default()
+            };
+            let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+            let plan = preview_media_move(State(regex.clone()), Extension(ctx.clone()), Json(req)).await.unwrap();
+
+            assert_eq!(1, plan.videos.len());
+            assert!(plan.dirs_to_delete.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn traversing_paths_rejected() {
+        let settings = create_test_settings();
+
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join("traversing movie");
+        let file = "traversing.mp4";
+        create_file(path.join(file), 6);
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let outside = PathBuf::from(&settings.filesystem.movies_path).to_string_lossy().into_owned();
+        let escaping = path.join("..").join("..").to_string_lossy().into_owned();
+        let media_path = path.to_string_lossy().into_owned();
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+
+        let invalid = [
+            (&outside, "some movie", file, "fileGroup.path"),
+            (&escaping, "some movie", file, "fileGroup.path"),
+            (&media_path, "../../etc", file, "name"),
+            (&media_path, "some movie", "../other.mp4", "fileGroup.videos"),
+            (&media_path, "some movie", "/etc/passwd", "fileGroup.videos"),
+        ];
+
+        for (media_path, name, video, field) in invalid {
+            let media = MediaFileGroup {
+                path: media_path.to_string(),
+                name: name.to_owned(),
+                videos: vec![video.to_owned()],
+                ..Default::default()
+            };
+            let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+            match preview_media_move(State(regex.clone()), Extension(ctx.clone()), Json(req)).await {
+                Err(Error::UnprocessableEntity { errors }) => assert!(errors.contains_key(field)),
+                _ => panic!("expected {} to be rejected", field),
+            }
+        }
+
+        let media = MediaFileGroup {
+            path: media_path,
+            name: "some movie".to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, name: Some("..".to_owned()), ..Default::default() };
+
+        match move_media(State(regex), Extension(ctx), Json(vec![req])).await {
+            Err(Error::UnprocessableEntity { errors }) => assert!(errors.contains_key("[0].name")),
+            _ => panic!("expected batch to be rejected"),
+        }
+        assert!(path.join(file).is_file());
+    }
+
+    #[tokio::test]
+    async fn subs_normalized_and_converted_on_import() {
+        let settings = create_test_settings();
+
+        let name = "some subtitled movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let file = "subtitled.mp4";
+        create_file(path.join(file), 6);
+
+        fs::write(path.join("subtitled.rum.srt"), b"1\n00:00:01,000 --> 00:00:02,000\n\xaai \xfeie \xeel place a\xbaa\n").unwrap();
+        fs::write(path.join("subtitled.eng.ass"), "[Events]\nFormat: Layer, Start, End, Style, Text\n\
+            Dialogue: 0,0:00:01.00,0:00:02.00,Default,Hello\n").unwrap();
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let resp = move_media(State(regex), Extension(ctx), Json(vec![req])).await.unwrap();
+
+        let movie_dest = Path::new(&settings.filesystem.movies_path).join(name);
+        assert!(resp[0].success);
+        assert_eq!("1\n00:00:01,000 --> 00:00:02,000\nŞi ţie îl place aşa\n", 
+            fs::read_to_string(movie_dest.join("subtitled.ro.srt")).unwrap());
+        assert_eq!("1\n00:00:01,000 --> 00:00:02,000\nHello\n", 
+            fs::read_to_string(movie_dest.join("subtitled.en.srt")).unwrap());
+    }
+
+    #[test]
+    fn failed_sub_conversions_keep_source_format_and_undo_restores_originals() {
+        let settings = create_test_settings();
+
+        let src_dir = PathBuf::from(&settings.filesystem.downloads_path).join("converted subs movie");
+        let dest_dir = PathBuf::from(&settings.filesystem.movies_path).join("Converted Subs Movie");
+        let ass = "[Events]\nFormat: Layer, Start, End, Style, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,Hello\n";
+        let broken = "[Events]\nFormat: Layer, Start, End, Style, Text\nDialogue: 0,0:xx:01.00,0:00:02.00,Default,Hello\n";
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::write(src_dir.join("converted.eng.ass"), ass).unwrap();
+        fs::write(src_dir.join("broken.eng.ass"), broken).unwrap();
+        fs::write(src_dir.join("plain.eng.srt"), "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+
+        let plan = MediaMovePlan {
+            subs: vec![
+                PlannedMove::new(&src_dir.join("converted.eng.ass"), &dest_dir.join("converted.en.srt")),
+                PlannedMove::new(&src_dir.join("broken.eng.ass"), &dest_dir.join("broken.en.srt")),
+                PlannedMove::new(&src_dir.join("plain.eng.srt"), &dest_dir.join("plain.en.srt")),
+            ],
+            ..Default::default()
+        };
+        let mut entry = MoveJournalEntry::new(src_dir.to_string_lossy().into_owned());
+        plan.execute(&settings, &mut entry).unwrap();
+
+        assert_eq!("1\n00:00:01,000 --> 00:00:02,000\nHello\n", fs::read_to_string(dest_dir.join("converted.en.srt")).unwrap());
+        assert_eq!(broken, fs::read_to_string(dest_dir.join("broken.en.ass")).unwrap());
+        assert!(!dest_dir.join("broken.en.srt").exists());
+        assert!(!dest_dir.join("converted.en.original").exists());
+        assert!(!dest_dir.join("plain.en.original").exists());
+        assert_eq!(1, entry.rewritten.len());
+
+        let result = journal::undo(&settings, &mut entry);
+
+        assert!(result.errors.is_empty());
+        assert_eq!(ass, fs::read_to_string(src_dir.join("converted.eng.ass")).unwrap());
+        assert_eq!(broken, fs::read_to_string(src_dir.join("broken.eng.ass")).unwrap());
+        assert!(!dest_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn selected_videos_never_treated_as_samples() {
+        let settings = create_test_settings();
+
+        let name = "The Sample (2020)";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join("The.Sample.2020.1080p");
+        let file = "The.Sample.2020.1080p.mp4";
+        let sample = "sample.mp4";
+        create_file(path.join(file), 6);
+        create_file(path.join(sample), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let plan = preview_media_move(State(regex), Extension(ctx), Json(req)).await.unwrap();
+
+        let movie_dest = Path::new(&settings.filesystem.movies_path).join(name);
+        assert_eq!(vec![PlannedMove::new(&path.join(file), &movie_dest.join(file))], plan.videos);
+        assert_eq!(vec![path.join(sample).to_string_lossy().into_owned()], plan.deleted);
+    }
+
+    #[tokio::test]
+    async fn release_contents_sorted_on_move() {
+        let settings = create_test_settings();
+
+        let name = "some release movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join("Some.Release.Movie.1080p");
+        let file = "release.mp4";
+        let sample = "Sample/release-sample.mp4";
+        let extra = "Featurettes/making.mp4";
+        create_file(path.join(file), 6);
+        create_file(path.join(sample), 6);
+        create_file(path.join(extra), 6);
+        create_file(path.join("release.nfo"), 1);
+        create_file(path.join("release.srt"), 1);
+        create_file(path.join("RARBG.txt"), 0);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let plan = preview_media_move(State(regex), Extension(ctx), Json(req)).await.unwrap();
+
+        let movie_dest = Path::new(&settings.filesystem.movies_path).join(name);
+        assert_eq!(vec![PlannedMove::new(&path.join(file), &movie_dest.join(file))], plan.videos);
+        assert_eq!(vec![PlannedMove::new(&path.join(extra), &movie_dest.join(EXTRAS_DIR).join("making.mp4"))], plan.extras);
+        assert_eq!(vec![PlannedMove::new(&path.join("release.srt"), &movie_dest.join("release.srt"))], plan.subs);
+        assert_eq!(vec![path.join("RARBG.txt").to_string_lossy().into_owned(), path.join(sample).to_string_lossy().into_owned()], 
+            plan.deleted);
+    }
+
+    #[tokio::test]
+    async fn move_job_runs_in_background() {
+        let settings = create_test_settings();
+
+        let name = "some background movie";
+        let path = PathBuf::from(&settings.filesystem.downloads_path).join(name);
+        let file = "background.mp4";
+        create_file(path.join(file), 6);
+
+        let media = MediaFileGroup {
+            path: path.to_string_lossy().into_owned(),
+            name: name.to_owned(),
+            videos: vec![file.to_owned()],
+            ..Default::default()
+        };
+
+        let regex = Arc::new(Regex::new(EPISODE_SEGMENT_REGEX).unwrap());
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+        let settings = Arc::new(settings);
+        let ctx = ApiContext { settings: settings.clone(), db_client, };
+        let req = MediaMoveReq { file_group: media, media_type: MediaFileType::MOVIE, ..Default::default() };
+
+        let job = submit_move_job(State(regex), Extension(ctx), Json(req)).await.unwrap();
+
+        assert_eq!(MoveJobStatus::QUEUED, job.status);
+        assert_eq!(fs::metadata(path.join(file)).unwrap().len(), job.bytes_total);
+
+        let dest = Path::new(&settings.filesystem.movies_path).join(name).join(file);
+        for _ in 0..50 {
+            if dest.is_file() && !path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        assert!(dest.is_file());
+        assert!(!path.exists());
+    }
+}
+
+#[cfg(test)]
+mod mongo_tests {
+    use std::sync::Arc;
+
+    use commander::{
+        db::DbClient,
+        mongo::MongoDbWrapper,
+        moving::{job::{MoveJob, MoveJobStatus}, journal::MoveJournalEntry, plan::PlannedMove},
+        tests::{create_mongo_image, create_test_settings, MONGO_PASS, MONGO_PORT, MONGO_USER},
+        trash::TrashItem,
+    };
+    use mongodb::Client;
+    use std::path::Path;
+    use testcontainers::clients;
+
+    #[tokio::test]
+    async fn move_jobs_are_persisted() {
+        let docker = clients::Cli::default();
+        let container = docker.run(create_mongo_image());
+
+        let mut settings = create_test_settings();
+        settings.mongodb.connection_url = format!("mongodb://{}:{}@localhost:{}/?retryWrites=true&w=majority",
+            MONGO_USER, MONGO_PASS, container.get_host_port_ipv4(MONGO_PORT)
+        );
+        let settings = Arc::new(settings);
+
+        let mongo_client = Client::with_uri_str(&settings.mongodb.connection_url).await.unwrap();
+        let db_wrapper = MongoDbWrapper::new(mongo_client, settings.clone());
+        let db_client = DbClient::new(Arc::new(db_wrapper));
+
+        let mut job = MoveJob::new("some/media/path".to_owned(), 100);
+        db_client.move_job_repo().save_job(&job).await.unwrap();
+
+        job.status = MoveJobStatus::RUNNING;
+        job.bytes_done = 40;
+        job.current_file = Some("video.mp4".to_owned());
+        db_client.move_job_repo().save_job(&job).await.unwrap();
+
+        let found = db_client.move_job_repo().find_job(&job.id).await.unwrap();
+        assert!(found.is_some());
+        let found = found.unwrap();
+        assert_eq!(MoveJobStatus::RUNNING, found.status);
+        assert_eq!(40, found.bytes_done);
+        assert_eq!(100, found.bytes_total);
+        assert_eq!(Some("video.mp4".to_owned()), found.current_file);
+
+        let page = db_client.move_job_repo().find_jobs(0, 10).await.unwrap();
+        assert_eq!(1, page.len());
+        assert_eq!(1, db_client.move_job_repo().count_jobs().await.unwrap());
+        assert_eq!(1, db_client.move_job_repo().find_unfinished_jobs().await.unwrap().len());
+        assert!(db_client.move_job_repo().find_jobs(1, 10).await.unwrap().is_empty());
+        assert!(db_client.move_job_repo().find_job("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn move_journal_entries_are_persisted() {
+        let docker = clients::Cli::default();
+        let container = docker.run(create_mongo_image());
+
+        let mut settings = create_test_settings();
+        settings.mongodb.connection_url = format!("mongodb://{}:{}@localhost:{}/?retryWrites=true&w=majority",
+            MONGO_USER, MONGO_PASS, container.get_host_port_ipv4(MONGO_PORT)
+        );
+        let settings = Arc::new(settings);
+
+        let mongo_client = Client::with_uri_str(&settings.mongodb.connection_url).await.unwrap();
+        let db_wrapper = MongoDbWrapper::new(mongo_client, settings.clone());
+        let db_client = DbClient::new(Arc::new(db_wrapper));
+
+        let mut entry = MoveJournalEntry::new("downloads/movie".to_owned());
+        entry.record_move(&PlannedMove::new(Path::new("downloads/movie/a.mp4"), Path::new("movies/Movie/a.mp4")));
+        entry.record_trashed(TrashItem { id: "trash_id".to_owned(), original_path: "downloads/movie".to_owned(), deleted: 1 });
+        db_client.move_journal_repo().save_entry(&entry).await.unwrap();
+
+        entry.undone = true;
+        db_client.move_journal_repo().save_entry(&entry).await.unwrap();
+
+        let found = db_client.move_journal_repo().find_entry(&entry.id).await.unwrap().unwrap();
+        assert!(found.undone);
+        assert_eq!(entry.moves, found.moves);
+        assert_eq!(entry.trashed, found.trashed);
     }
 }
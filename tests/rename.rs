@@ -111,10 +111,12 @@ mod cache_tests {
             search_name: base.name().to_owned(), 
             search_year: base.year(), 
             cover_path: String::new(), 
+            fanart_path: String::new(), 
             title: String::new(), 
             date: 0, 
             description: desc.to_owned(), 
             cast: vec![], 
+            actors: vec![], 
             media_type: MOVIE, 
         };
 
@@ -122,10 +124,12 @@ mod cache_tests {
             search_name: base.name().to_owned(), 
             search_year: base.year(), 
             cover_path: String::new(), 
+            fanart_path: String::new(), 
             title: String::new(), 
             date: 0, 
             description: desc.to_owned(), 
             cast: vec![], 
+            actors: vec![], 
             media_type: TV, 
         };
 
@@ -164,10 +168,12 @@ mod cache_tests {
             search_name: base.name().to_owned(), 
             search_year: base.year(), 
             cover_path: String::new(), 
+            fanart_path: String::new(), 
             title: String::new(), 
             date: 0, 
             description: desc.to_owned(), 
             cast: vec![], 
+            actors: vec![], 
             media_type: MOVIE, 
         };
 
@@ -175,10 +181,12 @@ mod cache_tests {
             search_name: base.name().to_owned(), 
             search_year: Some(2022), 
             cover_path: String::new(), 
+            fanart_path: String::new(), 
             title: String::new(), 
             date: 0, 
             description: desc.to_owned(), 
             cast: vec![], 
+            actors: vec![], 
             media_type: MOVIE, 
         };
 
@@ -253,10 +261,12 @@ mod tmdb_tests {
         let movie = ExternalMedia { 
             title: title.to_owned(), 
             poster_path: None, 
+            backdrop_path: None, 
             date: year.unwrap().to_string(), 
             description: String::new(), 
             id: 0, 
             cast: vec![],
+            actors: vec![],
         };
 
         let base = BaseInfo::new(title.to_owned(), year);
@@ -302,10 +312,12 @@ mod tmdb_tests {
         let tv = ExternalMedia { 
             title: title.to_owned(), 
             poster_path: Some(String::new()), 
+            backdrop_path: None, 
             date: year.unwrap().to_string(), 
             description: String::new(), 
             id: 0, 
             cast: vec![],
+            actors: vec![],
         };
 
         let base = BaseInfo::new(title.to_owned(), year);
@@ -1,12 +1,14 @@
 #[cfg(test)]
 mod tests {
-    use std::{path::PathBuf, sync::Arc};
+    use std::{fs, path::PathBuf, sync::Arc};
 
-    use axum::Extension;
+    use axum::{body::HttpBody, extract::Query, response::IntoResponse, Extension};
     use commander::{
+        config::NamedRoot,
         db::DbClient,
         http::ApiContext,
-        search::search_media,
+        rename::MediaFileType,
+        search::{search_archives, search_media, stream_media, ExcludedVideo, ExclusionReason, MediaFileGroup, MediaSearchParams},
         tests::create_test_settings,
     };
     use utils::tests::{EmptyDb, create_file};
@@ -47,6 +49,63 @@ mod tests {
     ]
     */
 
+    #[tokio::test]
+    async fn check_search_streams_media() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        create_file(downloads_path.join("video1.mp4"), 20);
+        create_file(downloads_path.join("show/Show.S01E01.mp4"), 20);
+        create_file(downloads_path.join("show/Show.S01E02.mp4"), 20);
+        create_file(downloads_path.join("movie/movie.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let response = stream_media(Extension(ctx), Query(MediaSearchParams { debug: None })).await.unwrap().into_response();
+        assert_eq!("application/x-ndjson", response.headers()["content-type"]);
+
+        let mut body = response.into_body();
+        let mut content = vec![];
+        while let Some(chunk) = body.data().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+
+        let mut videos: Vec<MediaFileGroup> = String::from_utf8(content).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        videos.sort_by(|v1, v2| v1.name.cmp(&v2.name));
+
+        assert_eq!(3, videos.len());
+        assert_eq!("movie", &videos[0].name);
+        assert_eq!("show", &videos[1].name);
+        assert_eq!(vec!["Show.S01E01.mp4".to_owned(), "Show.S01E02.mp4".to_owned()], videos[1].videos);
+        assert_eq!("video1", &videos[2].name);
+    }
+
+    #[tokio::test]
+    async fn check_search_covers_every_downloads_root() {
+        let mut settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        let seedbox_path = downloads_path.parent().unwrap().join("seedbox");
+        settings.filesystem.downloads_roots = vec![NamedRoot { name: "seedbox".to_owned(), path: seedbox_path.to_string_lossy().into_owned() }];
+        create_file(downloads_path.join("local/local.mp4"), 20);
+        create_file(seedbox_path.join("remote/remote.mp4"), 20);
+        create_file(seedbox_path.join("root.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let mut videos = search_media(Extension(ctx), Query(MediaSearchParams { debug: None })).await.unwrap();
+        videos.sort_by(|v1, v2| v1.name.cmp(&v2.name));
+
+        assert_eq!(3, videos.len());
+        assert_eq!(downloads_path.join("local").to_string_lossy(), videos[0].path);
+        assert_eq!(seedbox_path.join("remote").to_string_lossy(), videos[1].path);
+        assert_eq!(vec!["remote.mp4".to_owned()], videos[1].videos);
+        assert_eq!(seedbox_path.to_string_lossy(), videos[2].path);
+        assert_eq!("root", &videos[2].name);
+    }
+
     #[tokio::test]
     async fn check_search_finds_correct_media() {
         let settings = create_test_settings();
@@ -61,7 +120,7 @@ mod tests {
         create_file(downloads_path.join("1/2/3/4/5/deep.mp4"), 20);
 
         let ctx = ApiContext { settings: Arc::new(settings), db_client, };
-        let mut videos = search_media(Extension(ctx)).await.unwrap();
+        let mut videos = search_media(Extension(ctx), Query(MediaSearchParams { debug: None })).await.unwrap();
         videos.sort_by(|v1, v2| v1.name.cmp(&v2.name));
         videos.iter_mut().for_each(|mfg| mfg.videos.sort_by(|v1, v2| v1.cmp(v2)));
 
@@ -75,4 +134,122 @@ mod tests {
         assert_eq!(downloads_str, videos[2].path);
         assert_eq!("video3.mkv", &videos[2].videos[0]);
     }
+
+    #[tokio::test]
+    async fn check_search_finds_archives() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        let rar = b"Rar!\x1a\x07\x01\x00rest";
+        let zip = b"PK\x03\x04rest";
+        fs::create_dir_all(downloads_path.join("Show.S01")).unwrap();
+        fs::write(downloads_path.join("Show.S01/show.part01.rar"), rar).unwrap();
+        fs::write(downloads_path.join("Show.S01/show.part02.rar"), rar).unwrap();
+        fs::write(downloads_path.join("Show.S01/subs.zip"), zip).unwrap();
+        fs::write(downloads_path.join("Movie.2020.rar"), rar).unwrap();
+        fs::write(downloads_path.join("Movie.2020.r00"), rar).unwrap();
+        fs::write(downloads_path.join("fake.zip"), "not a zip").unwrap();
+        fs::create_dir_all(downloads_path.join("exclude")).unwrap();
+        fs::write(downloads_path.join("exclude/excluded.zip"), zip).unwrap();
+        create_file(downloads_path.join("video1.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let mut archives = search_archives(Extension(ctx)).await.unwrap();
+        archives.sort_by(|a1, a2| a1.name.cmp(&a2.name));
+        archives.iter_mut().for_each(|mag| mag.archives.sort());
+
+        let downloads_str = downloads_path.to_string_lossy().into_owned();
+        assert_eq!(2, archives.len());
+        assert_eq!(downloads_str, archives[0].path);
+        assert_eq!("Movie.2020", &archives[0].name);
+        assert_eq!(vec!["Movie.2020.rar"], archives[0].archives);
+        assert!(archives[1].path.ends_with("Show.S01"));
+        assert_eq!("Show.S01", &archives[1].name);
+        assert_eq!(vec!["show.part01.rar", "subs.zip"], archives[1].archives);
+    }
+
+    #[tokio::test]
+    async fn check_search_excludes_incomplete_and_samples() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        create_file(downloads_path.join("movie/movie.mkv"), 20);
+        create_file(downloads_path.join("movie/movie-sample.mkv"), 20);
+        create_file(downloads_path.join("movie/Sample/movie.mkv"), 20);
+        create_file(downloads_path.join("movie/movie2.mkv.part"), 20);
+        create_file(downloads_path.join("downloading.mp4.!qB"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let videos = search_media(Extension(ctx.clone()), Query(MediaSearchParams { debug: None })).await.unwrap();
+
+        assert_eq!(1, videos.len());
+        assert_eq!(vec!["movie.mkv"], videos[0].videos);
+        assert!(videos[0].excluded.is_empty());
+
+        let mut videos = search_media(Extension(ctx), Query(MediaSearchParams { debug: Some(true) })).await.unwrap();
+        videos.sort_by(|v1, v2| v1.name.cmp(&v2.name));
+        videos.iter_mut().for_each(|mfg| mfg.excluded.sort_by(|e1, e2| e1.video.cmp(&e2.video)));
+
+        assert_eq!(2, videos.len());
+        assert_eq!("downloading.mp4", &videos[0].name);
+        assert!(videos[0].videos.is_empty());
+        assert_eq!(vec![ExcludedVideo { video: "downloading.mp4.!qB".to_owned(), reason: ExclusionReason::INCOMPLETE }], videos[0].excluded);
+        assert_eq!(vec!["movie.mkv"], videos[1].videos);
+        assert_eq!(vec![
+            ExcludedVideo { video: "movie-sample.mkv".to_owned(), reason: ExclusionReason::SAMPLE },
+            ExcludedVideo { video: "movie2.mkv.part".to_owned(), reason: ExclusionReason::INCOMPLETE },
+        ], videos[1].excluded);
+    }
+
+    #[tokio::test]
+    async fn check_search_waits_for_quiet_period() {
+        let mut settings = create_test_settings();
+        settings.search.quiet_period_secs = 3600;
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        create_file(downloads_path.join("fresh.mp4"), 20);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        assert!(search_media(Extension(ctx.clone()), Query(MediaSearchParams { debug: None })).await.unwrap().is_empty());
+
+        let videos = search_media(Extension(ctx), Query(MediaSearchParams { debug: Some(true) })).await.unwrap();
+        assert_eq!(vec![ExcludedVideo { video: "fresh.mp4".to_owned(), reason: ExclusionReason::RECENT }], videos[0].excluded);
+    }
+
+    #[tokio::test]
+    async fn check_search_describes_groups() {
+        let settings = create_test_settings();
+        let db_client = DbClient::new(Arc::new(EmptyDb));
+
+        let downloads_path = PathBuf::from(&settings.filesystem.downloads_path);
+        create_file(downloads_path.join("Show.S02.1080p.WEB-DL/Show.S02E01.x265.mkv"), 20);
+        create_file(downloads_path.join("Show.S02.1080p.WEB-DL/Show.S02E02.x265.mkv"), 30);
+        create_file(downloads_path.join("Movie.2020.720p.BluRay.x264.mp4"), 40);
+
+        let ctx = ApiContext { settings: Arc::new(settings), db_client, };
+        let mut videos = search_media(Extension(ctx), Query(MediaSearchParams { debug: None })).await.unwrap();
+        videos.sort_by(|v1, v2| v1.name.cmp(&v2.name));
+        videos.iter_mut().for_each(|mfg| mfg.video_details.sort_by(|d1, d2| d1.video.cmp(&d2.video)));
+
+        assert_eq!(2, videos.len());
+        let movie = &videos[0];
+        assert_eq!(Some(MediaFileType::MOVIE), movie.suggested_type);
+        assert_eq!(movie.video_details[0].size, movie.total_size);
+        assert_eq!("video/mp4", &movie.video_details[0].mime_type);
+        assert!(movie.video_details[0].modified > 0);
+        assert_eq!(Some("720p".to_owned()), movie.video_details[0].quality.resolution);
+        assert_eq!(Some("BluRay".to_owned()), movie.video_details[0].quality.source);
+        assert_eq!(Some("AVC".to_owned()), movie.video_details[0].quality.codec);
+        assert_eq!((None, None), (movie.video_details[0].season, movie.video_details[0].episode));
+
+        let show = &videos[1];
+        assert_eq!(Some(MediaFileType::TV), show.suggested_type);
+        assert_eq!(show.video_details.iter().map(|d| d.size).sum::<u64>(), show.total_size);
+        assert_eq!((Some(2), Some(2)), (show.video_details[1].season, show.video_details[1].episode));
+        assert_eq!(Some("1080p".to_owned()), show.video_details[1].quality.resolution);
+        assert_eq!(Some("HEVC".to_owned()), show.video_details[1].quality.codec);
+    }
 }
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use commander::{tests::create_test_settings, trash};
+    use utils::tests::create_file;
+
+    #[test]
+    fn trashed_dir_restored() {
+        let settings = create_test_settings();
+
+        let dir = PathBuf::from(&settings.filesystem.downloads_path).join("trashed movie");
+        let file = "trashed.mp4";
+        create_file(dir.join(file), 6);
+
+        let item = trash::move_to_trash(&settings, &dir).unwrap();
+
+        assert!(!dir.exists());
+        assert_eq!(vec![item.clone()], trash::list_items(&settings).unwrap());
+
+        create_file(dir.join(file), 6);
+        assert!(trash::restore(&settings, &item).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        trash::restore(&settings, &item).unwrap();
+
+        assert!(dir.join(file).is_file());
+        assert!(trash::list_items(&settings).unwrap().is_empty());
+    }
+
+    #[test]
+    fn trash_emptied_and_purged() {
+        let mut settings = create_test_settings();
+
+        let first = PathBuf::from(&settings.filesystem.downloads_path).join("first.mp4");
+        let second = PathBuf::from(&settings.filesystem.downloads_path).join("second.mp4");
+        create_file(&first, 6);
+        create_file(&second, 6);
+
+        trash::move_to_trash(&settings, &first).unwrap();
+        assert!(trash::purge_expired(&settings).unwrap().is_empty());
+
+        settings.trash.retention_days = 0;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(1, trash::purge_expired(&settings).unwrap().len());
+
+        trash::move_to_trash(&settings, &second).unwrap();
+        assert_eq!(1, trash::empty(&settings).unwrap().len());
+        assert!(trash::list_items(&settings).unwrap().is_empty());
+        assert!(!first.exists());
+        assert!(!second.exists());
+    }
+}